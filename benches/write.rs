@@ -0,0 +1,73 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use brickadia::{
+    save::{Brick, BrickColor, Color, Header2, SaveData, Size},
+    write::SaveWriter,
+};
+
+const BRICKS: u32 = 10_000;
+
+/// A save of `BRICKS` bricks in a grid, with `color` giving each brick's color.
+fn save(color: impl Fn(u32) -> BrickColor) -> SaveData {
+    let mut save = SaveData {
+        header2: Header2::default().with_default_palette(),
+        ..Default::default()
+    };
+    save.bricks = (0..BRICKS)
+        .map(|i| Brick {
+            position: ((i % 100) as i32 * 10, (i / 100) as i32 * 10, 6),
+            size: Size::Procedural(5, 5, 6),
+            color: color(i),
+            ..Default::default()
+        })
+        .collect();
+    save
+}
+
+fn unique(i: u32) -> BrickColor {
+    BrickColor::Unique(Color::from_rgba(i as u8, (i >> 8) as u8, 128, 255))
+}
+
+fn bricks(c: &mut Criterion) {
+    let saves = [
+        ("indexed", save(|i| BrickColor::Index(i % 96))),
+        ("unique", save(unique)),
+        (
+            "mixed",
+            save(|i| {
+                if i % 10 == 0 {
+                    unique(i)
+                } else {
+                    BrickColor::Index(i % 96)
+                }
+            }),
+        ),
+    ];
+
+    // encoding alone, without compression
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Elements(BRICKS as u64));
+    for (name, save) in saves.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), save, |b, save| {
+            b.iter(|| SaveWriter::new(vec![], save).write_sections().unwrap())
+        });
+    }
+    group.finish();
+
+    // a whole save, compressed at the default level
+    let mut group = c.benchmark_group("write");
+    group.throughput(Throughput::Elements(BRICKS as u64));
+    for (name, save) in saves.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), save, |b, save| {
+            b.iter(|| {
+                let mut bytes = vec![];
+                SaveWriter::new(&mut bytes, save).write().unwrap();
+                black_box(bytes)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bricks);
+criterion_main!(benches);
@@ -4,7 +4,7 @@ use brickadia::read::SaveReader;
 
 fn main() {
     let read_location = env::args().nth(1).unwrap_or("examples/read.brs".into());
-    let mut reader = SaveReader::new(File::open(read_location).unwrap()).unwrap();
+    let mut reader = SaveReader::open(read_location).unwrap();
     println!("Initialized reader, version: {}", reader.version);
     let header1 = reader.read_header1().unwrap();
     println!("Read header 1: {:?}\n", header1);
@@ -4,12 +4,13 @@ use brickadia::{read::SaveReader, write::SaveWriter};
 
 fn main() {
     let read_location = env::args().nth(1).unwrap_or("examples/read.brs".into());
-    let mut reader = SaveReader::new(File::open(read_location).unwrap()).unwrap();
+    let mut reader = SaveReader::open(read_location).unwrap();
     let save = reader.read_all().unwrap();
-    SaveWriter::new(File::create("examples/read.out.brs").unwrap(), save)
+    SaveWriter::create("examples/read.out.brs", save)
+        .unwrap()
         .write()
         .unwrap();
-    let mut reader = SaveReader::new(File::open("examples/read.out.brs").unwrap()).unwrap();
+    let mut reader = SaveReader::open("examples/read.out.brs").unwrap();
 
     println!("Initialized reader, version: {}", reader.version);
     let header1 = reader.read_header1().unwrap();
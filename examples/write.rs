@@ -1,4 +1,4 @@
-use std::{env, fs::File};
+use std::env;
 
 use brickadia::{
     save::{Brick, BrickColor, BrickOwner, Color, Preview, SaveData, Size, User},
@@ -24,22 +24,22 @@ fn main() {
         .push(BrickOwner::from_user_bricks(me.clone(), 100));
 
     // set the preview image
-    let preview_bytes = std::fs::read("examples/write_preview.png").unwrap();
-    save.preview = Preview::PNG(preview_bytes);
+    save.preview = Preview::from_png_file("examples/write_preview.png").unwrap();
 
     // add some bricks
     for y in 0..10 {
         for x in 0..10 {
-            let mut brick = Brick::default();
-            brick.position = (x * 10, y * 10, 10);
-            brick.size = Size::Procedural(5, 5, 6);
-            brick.color = BrickColor::Unique(Color {
-                r: (x as f32 / 10.0 * 255.0) as u8,
-                g: 255,
-                b: (y as f32 / 10.0 * 255.0) as u8,
-                a: 255,
+            save.bricks.push(Brick {
+                position: (x * 10, y * 10, 10),
+                size: Size::Procedural(5, 5, 6),
+                color: BrickColor::Unique(Color {
+                    r: (x as f32 / 10.0 * 255.0) as u8,
+                    g: 255,
+                    b: (y as f32 / 10.0 * 255.0) as u8,
+                    a: 255,
+                }),
+                ..Default::default()
             });
-            save.bricks.push(brick);
         }
     }
 
@@ -47,7 +47,8 @@ fn main() {
     let save_location = env::args()
         .nth(1)
         .unwrap_or("examples/write.out.brs".into());
-    SaveWriter::new(File::create(save_location).unwrap(), save)
+    SaveWriter::create(save_location, save)
+        .unwrap()
         .write()
         .unwrap();
 
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::save::{
+    Brick, BrickColor, BrickOwner, Color, Component, Header1, Header2, SaveData, User,
+};
+
+/// A builder for a `SaveData`, which fills in `Header2`'s tables from the bricks added to it.
+///
+/// Assets, materials, physical materials and colors are given by name (or value) and
+/// interned into their tables, so brick indices always refer to existing entries.
+#[derive(Debug)]
+pub struct SaveBuilder {
+    data: SaveData,
+    assets: HashMap<String, u32>,
+    materials: HashMap<String, u32>,
+    physical_materials: HashMap<String, u32>,
+    colors: HashMap<Color, u32>,
+    owners: HashMap<Uuid, u32>,
+}
+
+impl Default for SaveBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the index of `name` in `table`, adding it if it is not there.
+fn intern(table: &mut Vec<String>, indices: &mut HashMap<String, u32>, name: &str) -> u32 {
+    if let Some(&index) = indices.get(name) {
+        return index;
+    }
+
+    table.push(name.to_owned());
+    let index = table.len() as u32 - 1;
+    indices.insert(name.to_owned(), index);
+    index
+}
+
+impl SaveBuilder {
+    /// Create a builder for a save with empty `Header2` tables.
+    pub fn new() -> Self {
+        SaveBuilder {
+            data: SaveData {
+                header2: Header2 {
+                    brick_assets: vec![],
+                    materials: vec![],
+                    physical_materials: vec![],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            assets: HashMap::new(),
+            materials: HashMap::new(),
+            physical_materials: HashMap::new(),
+            colors: HashMap::new(),
+            owners: HashMap::new(),
+        }
+    }
+
+    /// Create a builder with room for `capacity` bricks.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut builder = Self::new();
+        builder.data.reserve_bricks(capacity);
+        builder
+    }
+
+    /// Get the save's first header, to set its map, description, author, etc.
+    pub fn header1(&mut self) -> &mut Header1 {
+        &mut self.data.header1
+    }
+
+    /// Get the save's components, to describe the components added to bricks.
+    pub fn components(&mut self) -> &mut HashMap<String, Component> {
+        &mut self.data.components
+    }
+
+    /// Get the index of the brick asset `name`, adding it if needed.
+    pub fn asset_index(&mut self, name: &str) -> u32 {
+        intern(&mut self.data.header2.brick_assets, &mut self.assets, name)
+    }
+
+    /// Get the index of the material `name`, adding it if needed.
+    pub fn material_index(&mut self, name: &str) -> u32 {
+        intern(&mut self.data.header2.materials, &mut self.materials, name)
+    }
+
+    /// Get the index of the physical material `name`, adding it if needed.
+    pub fn physical_material_index(&mut self, name: &str) -> u32 {
+        intern(
+            &mut self.data.header2.physical_materials,
+            &mut self.physical_materials,
+            name,
+        )
+    }
+
+    /// Get the index of `color` in the palette, adding it if needed.
+    pub fn color_index(&mut self, color: &Color) -> u32 {
+        let colors = &mut self.data.header2.colors;
+        *self.colors.entry(color.clone()).or_insert_with(|| {
+            colors.push(color.clone());
+            colors.len() as u32 - 1
+        })
+    }
+
+    /// Get the owner index (1-indexed, see `Brick::owner_index`) of `user`, adding them as
+    /// a brick owner if needed. Users are told apart by their ID.
+    pub fn owner_index(&mut self, user: &User) -> u32 {
+        let owners = &mut self.data.header2.brick_owners;
+        *self.owners.entry(user.id).or_insert_with(|| {
+            owners.push(BrickOwner::from(user.clone()));
+            owners.len() as u32
+        })
+    }
+
+    /// Add a brick as-is. Its indices should come from this builder's index methods.
+    pub fn add_brick(&mut self, brick: Brick) -> usize {
+        self.data.bricks.push(brick);
+        self.data.bricks.len() - 1
+    }
+
+    /// Add a brick using the brick asset, material, physical material and palette color
+    /// given, overwriting the brick's own indices for them. Returns the brick's index.
+    pub fn add_brick_named(
+        &mut self,
+        asset: &str,
+        material: &str,
+        physical_material: &str,
+        color: &Color,
+        brick: Brick,
+    ) -> usize {
+        let brick = Brick {
+            asset_name_index: self.asset_index(asset),
+            material_index: self.material_index(material),
+            physical_index: self.physical_material_index(physical_material),
+            color: BrickColor::Index(self.color_index(color)),
+            ..brick
+        };
+        self.add_brick(brick)
+    }
+
+    /// Finish the save, filling in the brick counts and component brick indices.
+    ///
+    /// Bricks whose owner index is out of range are not counted towards any owner.
+    pub fn build(mut self) -> SaveData {
+        self.data.header1.brick_count = self.data.bricks.len() as u32;
+        self.data.recount_owners();
+        self.data.reconcile_components();
+        self.data
+    }
+}
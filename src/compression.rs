@@ -0,0 +1,164 @@
+//! The framing used by the compressed sections of a save.
+//!
+//! A section is written as its uncompressed size (`i32`), its compressed size (`i32`), then its
+//! bytes. A compressed size of 0 means the bytes are stored uncompressed, which the writer
+//! does whenever zlib would not make them smaller.
+
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Write},
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flate2::read::ZlibDecoder;
+
+pub use flate2::Compression;
+
+use crate::{
+    read::ReadError,
+    write::{Compressor, FlateCompressor},
+};
+
+/// Write `bytes` to `writer` as a section compressed at `level`.
+///
+/// `Compression::none()` always stores the bytes uncompressed.
+pub fn write_compressed(
+    writer: &mut impl Write,
+    bytes: &[u8],
+    level: Compression,
+) -> io::Result<()> {
+    if level == Compression::none() {
+        write_section(writer, bytes, None)
+    } else {
+        write_section(writer, bytes, Some(&FlateCompressor(level)))
+    }
+}
+
+/// Read a section from `reader`, decompressing it if needed.
+///
+/// Fails with `ReadError::CorruptSection` if the section is truncated, or its compressed data
+/// is invalid or decompresses to less than the declared size, and with
+/// `ReadError::SizeMismatch` if it decompresses to more.
+pub fn read_compressed(reader: &mut impl Read) -> Result<Vec<u8>, ReadError> {
+    read_section(reader, "compressed")
+}
+
+/// Read the section named `section` from `reader`, like `read_compressed`.
+pub(crate) fn read_section(
+    reader: &mut impl Read,
+    section: &'static str,
+) -> Result<Vec<u8>, ReadError> {
+    let (uncompressed_size, compressed_size) = read_sizes(reader)?;
+
+    let expected = uncompressed_size as usize;
+    let corrupt = |got| ReadError::CorruptSection {
+        section,
+        expected,
+        got,
+    };
+
+    // the buffer grows with the data actually read, so a bogus
+    // uncompressed size can't make us allocate a huge buffer up front
+    let mut bytes = vec![];
+
+    if compressed_size == 0 {
+        // no need to decompress first
+        reader
+            .by_ref()
+            .take(uncompressed_size)
+            .read_to_end(&mut bytes)?;
+    } else {
+        // decompress straight from the reader, producing no more than the uncompressed size.
+        // a truncated or invalid stream fails to decode, keeping what was decoded before it
+        let mut decoder = ZlibDecoder::new(reader.by_ref().take(compressed_size));
+        decoder
+            .by_ref()
+            .take(uncompressed_size)
+            .read_to_end(&mut bytes)
+            .map_err(|e| decode_error(e, || corrupt(bytes.len())))?;
+
+        // the stream must end here, or it would decompress past the uncompressed size
+        if decoder
+            .read(&mut [0u8])
+            .map_err(|e| decode_error(e, || corrupt(expected)))?
+            != 0
+        {
+            return Err(ReadError::SizeMismatch);
+        }
+
+        // skip anything left of the compressed data, like the checksum
+        io::copy(&mut decoder.into_inner(), &mut io::sink())?;
+    }
+
+    if bytes.len() != expected {
+        return Err(corrupt(bytes.len()));
+    }
+
+    Ok(bytes)
+}
+
+/// Read the uncompressed and compressed sizes a section starts with. A compressed size of 0
+/// means the section is stored uncompressed.
+pub(crate) fn read_sizes(reader: &mut impl Read) -> Result<(u64, u64), ReadError> {
+    let (uncompressed_size, compressed_size) = (
+        reader.read_i32::<LittleEndian>()?,
+        reader.read_i32::<LittleEndian>()?,
+    );
+    // an empty section is stored uncompressed, as 0 and 0
+    if uncompressed_size < 0
+        || compressed_size < 0
+        || (compressed_size != 0 && compressed_size >= uncompressed_size)
+    {
+        return Err(ReadError::InvalidCompression);
+    }
+    Ok((uncompressed_size as u64, compressed_size as u64))
+}
+
+/// Turn an error from decoding a section into `corrupt()` if the data was bad, rather than
+/// the reader failing.
+fn decode_error(e: io::Error, corrupt: impl FnOnce() -> ReadError) -> ReadError {
+    match e.kind() {
+        io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+            corrupt()
+        }
+        _ => e.into(),
+    }
+}
+
+/// Write `bytes` to `writer` as a section, compressing them with `compressor` if that makes
+/// them smaller. When `compressor` is `None`, the section is always stored uncompressed.
+pub(crate) fn write_section(
+    writer: &mut impl Write,
+    bytes: &[u8],
+    compressor: Option<&dyn Compressor>,
+) -> io::Result<()> {
+    if i32::try_from(bytes.len()).is_err() {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+
+    let compressor = match compressor {
+        Some(compressor) => compressor,
+        None => {
+            writer.write_i32::<LittleEndian>(bytes.len() as i32)?;
+            writer.write_i32::<LittleEndian>(0)?;
+            writer.write_all(bytes)?;
+            return Ok(());
+        }
+    };
+
+    let compressed = compressor.compress(bytes)?;
+
+    writer.write_i32::<LittleEndian>(bytes.len() as i32)?;
+
+    if compressed.len() < bytes.len() {
+        // compressed is smaller, write (unc_size: i32, c_size: i32, bytes)
+        writer.write_i32::<LittleEndian>(compressed.len() as i32)?;
+        writer.write_all(&compressed)?;
+    } else {
+        // write uncompressed (unc_size: i32, c_size: i32 = 0, bytes)
+        writer.write_i32::<LittleEndian>(0)?;
+        writer.write_all(bytes)?;
+    }
+
+    Ok(())
+}
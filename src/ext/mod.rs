@@ -1,2 +1,87 @@
 pub mod read;
 pub mod write;
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use bitstream_io::{BitRead, BitReader, BitWrite, BitWriter, LittleEndian};
+
+    use super::{
+        read::{BitReadExt, ReadExt},
+        write::{BitWriteExt, WriteExt},
+    };
+    use crate::save::{Color, UnrealValue};
+
+    const STRINGS: &[&str] = &["", "a", "BCD_Interact", "héllo", "日本語", "🧱"];
+
+    #[test]
+    fn unreal_values_round_trip() {
+        let values = vec![
+            UnrealValue::Boolean(true),
+            UnrealValue::Boolean(false),
+            UnrealValue::Byte(0),
+            UnrealValue::Byte(255),
+            UnrealValue::Float(0.0),
+            UnrealValue::Float(-0.0),
+            UnrealValue::Float(1.5),
+            UnrealValue::Float(-1.5),
+            UnrealValue::Float(f32::MIN),
+            UnrealValue::Float(f32::MAX),
+            UnrealValue::Float(f32::MIN_POSITIVE),
+            UnrealValue::Float(f32::INFINITY),
+            UnrealValue::Float(f32::NEG_INFINITY),
+            UnrealValue::Color(Color {
+                r: 1,
+                g: 2,
+                b: 3,
+                a: 4,
+            }),
+            UnrealValue::Rotator(-90.0, 0.0, 180.5),
+        ]
+        .into_iter()
+        .chain(STRINGS.iter().map(|&s| UnrealValue::String(s.into())))
+        .chain(STRINGS.iter().map(|&s| UnrealValue::Class(s.into())))
+        .collect::<Vec<_>>();
+
+        for value in values {
+            let mut bytes = vec![];
+            let mut bits = BitWriter::endian(&mut bytes, LittleEndian);
+            bits.write_unreal(&value).unwrap();
+            // pad with set bits so reading past the value is caught below
+            bits.write(7, 0b111_1111u8).unwrap();
+            bits.byte_align().unwrap();
+
+            let mut bits = BitReader::endian(Cursor::new(&bytes), LittleEndian);
+            let read = bits.read_unreal_type(value.type_name()).unwrap();
+            assert_eq!(bits.read::<u8>(7).unwrap(), 0b111_1111, "{:?}", value);
+
+            match (&value, &read) {
+                // -0.0 == 0.0, so compare the bits
+                (UnrealValue::Float(a), UnrealValue::Float(b)) => {
+                    assert_eq!(a.to_bits(), b.to_bits())
+                }
+                _ => assert_eq!(value, read),
+            }
+        }
+    }
+
+    #[test]
+    fn strings_round_trip() {
+        for &string in STRINGS {
+            let mut bytes = vec![];
+            bytes.write_string(string).unwrap();
+            let mut cursor = Cursor::new(&bytes);
+            assert_eq!(cursor.read_string().unwrap(), string);
+            assert_eq!(cursor.position() as usize, bytes.len(), "{:?}", string);
+        }
+    }
+
+    #[test]
+    fn wide_string_encoding() {
+        // "é" as UCS-2: a length of -2 code units, the character, and a wide null terminator
+        let mut bytes = vec![];
+        bytes.write_string("é").unwrap();
+        assert_eq!(bytes, [0xfe, 0xff, 0xff, 0xff, 0xe9, 0x00, 0x00, 0x00]);
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     cmp,
+    convert::TryFrom,
     io::{self, Read, Result},
 };
 
@@ -7,7 +8,25 @@ use bitstream_io::BitRead;
 use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
 use uuid::Uuid;
 
-use crate::save::{Color, UnrealType};
+use crate::save::{Color, UnrealValue};
+
+/// The most elements an array is presized for, so that a bogus length can't make us allocate
+/// a huge buffer up front.
+const MAX_PRESIZE: usize = 4096;
+
+/// Check a length read from a save, which must not be negative.
+pub(crate) fn check_len(len: i32) -> Result<usize> {
+    usize::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "negative length"))
+}
+
+/// Decode a UCS-2 string read from a save, dropping its null terminator.
+fn utf16_string(mut chars: Vec<u16>) -> Result<String> {
+    if chars.last() == Some(&0) {
+        chars.pop();
+    }
+    String::from_utf16(&chars)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid UCS-2 string data"))
+}
 
 pub trait ReadExt: Read {
     fn read_string(&mut self) -> Result<String> {
@@ -21,24 +40,12 @@ pub trait ReadExt: Read {
                 String::from_utf8(chars)
                     .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid string data"))
             }
-            size if size < 0 => {
-                let size = -size;
-                match size % 2 {
-                    0 => {
-                        let mut chars = vec![0; size as usize / 2];
-                        self.read_u16_into::<LittleEndian>(&mut chars)?;
-                        String::from_utf16(&chars).map_err(|_| {
-                            io::Error::new(io::ErrorKind::InvalidData, "invalid UCS-2 string data")
-                        })
-                    }
-                    1 => Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "invalid UCS-2 size",
-                    )),
-                    _ => unreachable!(),
-                }
+            size => {
+                // ucs-2: negative length, in code units, including the null terminator
+                let mut chars = vec![0; size.unsigned_abs() as usize];
+                self.read_u16_into::<LittleEndian>(&mut chars)?;
+                utf16_string(chars)
             }
-            _ => unreachable!(),
         }
     }
 
@@ -54,8 +61,8 @@ pub trait ReadExt: Read {
     where
         F: FnMut(&mut Self) -> Result<T>,
     {
-        let len = self.read_i32::<LittleEndian>()?;
-        let mut vec = Vec::with_capacity(len as usize);
+        let len = check_len(self.read_i32::<LittleEndian>()?)?;
+        let mut vec = Vec::with_capacity(cmp::min(len, MAX_PRESIZE));
         for _ in 0..len {
             vec.push(operation(self)?);
         }
@@ -70,8 +77,8 @@ pub trait BitReadExt: BitRead {
     where
         F: FnMut(&mut Self) -> Result<T>,
     {
-        let len = self.read_i32_le()?;
-        let mut vec = Vec::with_capacity(len as usize);
+        let len = check_len(self.read_i32_le()?)?;
+        let mut vec = Vec::with_capacity(cmp::min(len, MAX_PRESIZE));
         for _ in 0..len {
             vec.push(operation(self)?);
         }
@@ -126,24 +133,12 @@ pub trait BitReadExt: BitRead {
                 String::from_utf8(chars)
                     .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid string data"))
             }
-            size if size < 0 => {
-                let size = -size * 2;
-                match size % 2 {
-                    0 => {
-                        let mut chars = vec![0; (size / 2) as usize];
-                        self.read_u16_le_into(&mut chars)?;
-                        String::from_utf16(&chars).map_err(|_| {
-                            io::Error::new(io::ErrorKind::InvalidData, "invalid UCS-2 string data")
-                        })
-                    }
-                    1 => Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "invalid UCS-2 size",
-                    )),
-                    _ => unreachable!(),
-                }
+            size => {
+                // ucs-2: negative length, in code units, including the null terminator
+                let mut chars = vec![0; size.unsigned_abs() as usize];
+                self.read_u16_le_into(&mut chars)?;
+                utf16_string(chars)
             }
-            _ => unreachable!(),
         }
     }
 
@@ -182,23 +177,23 @@ pub trait BitReadExt: BitRead {
         Ok(LittleEndian::read_f32(&bytes))
     }
 
-    fn read_unreal_type(&mut self, t: &str) -> Result<UnrealType> {
+    fn read_unreal_type(&mut self, t: &str) -> Result<UnrealValue> {
         match t {
-            "Class" | "Object" => Ok(UnrealType::Class(self.read_string()?)),
-            "String" => Ok(UnrealType::String(self.read_string()?)),
-            "Boolean" => Ok(UnrealType::Boolean(self.read_i32_le()? != 0)),
-            "Float" => Ok(UnrealType::Float(self.read_f32_le()?)),
+            "Class" | "Object" => Ok(UnrealValue::Class(self.read_string()?)),
+            "String" => Ok(UnrealValue::String(self.read_string()?)),
+            "Boolean" => Ok(UnrealValue::Boolean(self.read_i32_le()? != 0)),
+            "Float" => Ok(UnrealValue::Float(self.read_f32_le()?)),
             "Color" => {
                 let mut bytes = [0u8; 4];
                 self.read_bytes(&mut bytes)?;
-                Ok(UnrealType::Color(Color::from_bytes_bgra(bytes)))
+                Ok(UnrealValue::Color(Color::from_bytes_bgra(bytes)))
             }
             "Byte" => {
                 let mut byte = [0u8; 1];
                 self.read_bytes(&mut byte)?;
-                Ok(UnrealType::Byte(byte[0]))
+                Ok(UnrealValue::Byte(byte[0]))
             }
-            "Rotator" => Ok(UnrealType::Rotator(
+            "Rotator" => Ok(UnrealValue::Rotator(
                 self.read_f32_le()?,
                 self.read_f32_le()?,
                 self.read_f32_le()?,
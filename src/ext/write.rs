@@ -1,13 +1,21 @@
-use std::io::{self, Write};
+use std::{
+    convert::TryFrom,
+    io::{self, Write},
+};
 
 use bitstream_io::BitWrite;
 use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
 use uuid::Uuid;
 
-use crate::save::{Color, UnrealType};
+use crate::save::{Color, UnrealValue};
+
+/// Convert a length to the `i32` it is written as, failing if it doesn't fit.
+fn len_i32(len: usize) -> io::Result<i32> {
+    i32::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
 
 pub trait WriteExt: Write {
-    fn write_string(&mut self, string: String) -> io::Result<()> {
+    fn write_string(&mut self, string: &str) -> io::Result<()> {
         if string.is_empty() {
             // write out a 0 and nothing else
             self.write_i32::<LittleEndian>(0)?;
@@ -16,22 +24,20 @@ pub trait WriteExt: Write {
 
         if string.is_ascii() {
             // write utf-8: positive length
-            self.write_i32::<LittleEndian>(string.len() as i32 + 1)?;
-            for c in string.chars() {
-                self.write_u8(c as u8)?;
-            }
+            self.write_i32::<LittleEndian>(len_i32(string.len() + 1)?)?;
+            self.write_all(string.as_bytes())?;
             self.write_u8(0)?; // write a null terminator
 
             Ok(())
         } else {
-            // write ucs-2: negative length
+            // write ucs-2: negative length, in code units, including the null terminator
             let utf16 = string.encode_utf16();
-            let len = -((utf16.clone().count() as i32 + 1) * 2);
+            let len = -len_i32(utf16.clone().count() + 1)?;
             self.write_i32::<LittleEndian>(len)?;
             for c in utf16 {
                 self.write_u16::<LittleEndian>(c)?;
             }
-            self.write_u8(0)?; // write a null terminator
+            self.write_u16::<LittleEndian>(0)?; // write a wide null terminator
 
             Ok(())
         }
@@ -47,21 +53,17 @@ pub trait WriteExt: Write {
         Ok(())
     }
 
-    fn write_color_bgra(&mut self, color: Color) -> io::Result<()> {
-        self.write_u8(color.b)?;
-        self.write_u8(color.g)?;
-        self.write_u8(color.r)?;
-        self.write_u8(color.a)?;
-        Ok(())
+    fn write_color_bgra(&mut self, color: &Color) -> io::Result<()> {
+        self.write_all(&color.to_bgra_bytes())
     }
 
-    fn write_array<F: FnMut(&mut Self, T) -> io::Result<()>, T>(
+    fn write_array<F: FnMut(&mut Self, &T) -> io::Result<()>, T>(
         &mut self,
-        vec: Vec<T>,
+        vec: &[T],
         mut operation: F,
     ) -> io::Result<()> {
-        self.write_i32::<LittleEndian>(vec.len() as i32)?;
-        for item in vec.into_iter() {
+        self.write_i32::<LittleEndian>(len_i32(vec.len())?)?;
+        for item in vec {
             operation(self, item)?;
         }
         Ok(())
@@ -83,25 +85,28 @@ pub trait BitWriteExt: BitWrite {
         self.write_bytes(&bytes)
     }
 
-    fn write_string(&mut self, string: String) -> io::Result<()> {
+    fn write_string(&mut self, string: &str) -> io::Result<()> {
+        if string.is_empty() {
+            // write out a 0 and nothing else
+            return self.write_i32(0);
+        }
+
         if string.is_ascii() {
             // write utf-8: positive length
-            self.write_i32(string.len() as i32 + 1)?;
-            for c in string.chars() {
-                self.write_bytes(&[c as u8])?;
-            }
+            self.write_i32(len_i32(string.len() + 1)?)?;
+            self.write_bytes(string.as_bytes())?;
             self.write_bytes(&[0])?; // write a null terminator
 
             Ok(())
         } else {
-            // write ucs-2: negative length
+            // write ucs-2: negative length, in code units, including the null terminator
             let utf16 = string.encode_utf16();
-            let len = -((utf16.clone().count() as i32 + 1) * 2);
+            let len = -len_i32(utf16.clone().count() + 1)?;
             self.write_i32(len)?;
             for c in utf16 {
                 self.write_u16(c)?;
             }
-            self.write_bytes(&[0])?; // write a null terminator
+            self.write_u16(0)?; // write a wide null terminator
 
             Ok(())
         }
@@ -114,33 +119,29 @@ pub trait BitWriteExt: BitWrite {
             return Err(io::Error::from(io::ErrorKind::InvalidInput));
         }
 
+        // count the bits Unreal would write one at a time, then write them in one go
         let mut new_value = 0;
-        let mut mask = 1;
+        let mut mask = 1u32;
+        let mut bits = 0;
 
         while new_value + mask < max && mask != 0 {
-            self.write_bit(value & mask != 0)?;
-            if value & mask != 0 {
-                new_value |= mask;
-            }
+            new_value |= value & mask;
             mask <<= 1;
+            bits += 1;
         }
 
-        Ok(())
-    }
-
-    fn write_bits(&mut self, src: &[u8], len: usize) -> io::Result<()> {
-        for bit in 0..len {
-            self.write_bit((src[bit >> 3] & (1 << (bit & 7))) != 0)?;
+        if bits > 0 {
+            self.write(bits, value & mask.wrapping_sub(1))?;
         }
         Ok(())
     }
 
     fn write_uint_packed(&mut self, mut value: u32) -> io::Result<()> {
         loop {
-            let src = [(value & 0b111_1111) as u8];
+            let low = value & 0b111_1111;
             value >>= 7;
             self.write_bit(value != 0)?;
-            self.write_bits(&src, 7)?;
+            self.write(7, low)?;
             if value == 0 {
                 break;
             }
@@ -149,7 +150,12 @@ pub trait BitWriteExt: BitWrite {
     }
 
     fn write_int_packed(&mut self, value: i32) -> io::Result<()> {
-        self.write_uint_packed(((value.abs() as u32) << 1) | if value >= 0 { 1 } else { 0 })
+        // the magnitude is shifted left by one for the sign bit, so i32::MIN doesn't fit
+        if value == i32::MIN {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+
+        self.write_uint_packed((value.unsigned_abs() << 1) | if value >= 0 { 1 } else { 0 })
     }
 
     fn write_f32(&mut self, value: f32) -> io::Result<()> {
@@ -164,7 +170,7 @@ pub trait BitWriteExt: BitWrite {
         mut operation: F,
     ) -> io::Result<()> {
         let mut len_bytes = [0u8; 4];
-        LittleEndian::write_i32(&mut len_bytes, vec.len() as i32);
+        LittleEndian::write_i32(&mut len_bytes, len_i32(vec.len())?);
         self.write_bytes(&len_bytes)?;
 
         for item in vec {
@@ -173,18 +179,18 @@ pub trait BitWriteExt: BitWrite {
         Ok(())
     }
 
-    fn write_unreal(&mut self, unreal: UnrealType) -> io::Result<()> {
+    fn write_unreal(&mut self, unreal: &UnrealValue) -> io::Result<()> {
         match unreal {
-            UnrealType::Boolean(bool) => self.write_i32(if bool { 1 } else { 0 })?,
-            UnrealType::Byte(byte) => self.write_bytes(&[byte])?,
-            UnrealType::Class(str) => self.write_string(str)?,
-            UnrealType::String(str) => self.write_string(str)?,
-            UnrealType::Color(color) => self.write_bytes(&[color.b, color.g, color.r, color.a])?,
-            UnrealType::Float(float) => self.write_f32(float)?,
-            UnrealType::Rotator(x, y, z) => {
-                self.write_f32(x)?;
-                self.write_f32(y)?;
-                self.write_f32(z)?;
+            UnrealValue::Boolean(bool) => self.write_i32(if *bool { 1 } else { 0 })?,
+            UnrealValue::Byte(byte) => self.write_bytes(&[*byte])?,
+            UnrealValue::Class(str) => self.write_string(str)?,
+            UnrealValue::String(str) => self.write_string(str)?,
+            UnrealValue::Color(color) => self.write_bytes(&color.to_bgra_bytes())?,
+            UnrealValue::Float(float) => self.write_f32(*float)?,
+            UnrealValue::Rotator(x, y, z) => {
+                self.write_f32(*x)?;
+                self.write_f32(*y)?;
+                self.write_f32(*z)?;
             }
         }
         Ok(())
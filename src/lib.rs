@@ -1,6 +1,10 @@
+pub mod builder;
+pub mod compression;
 mod ext;
 pub mod read;
 pub mod save;
+pub mod shared;
+pub mod validate;
 pub mod write;
 
 #[cfg(feature = "util")]
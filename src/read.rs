@@ -1,19 +1,22 @@
 use std::{
+    cell::RefCell,
     cmp,
     collections::HashMap,
-    convert::TryFrom,
-    io::{self, Cursor, Read},
+    fs::File,
+    io::{self, BufReader, Cursor, Read},
+    path::Path,
 };
 
 use bitstream_io::{BitRead, BitReader};
 use byteorder::{LittleEndian, ReadBytesExt};
 use flate2::read::ZlibDecoder;
 use thiserror::Error;
+use uuid::Uuid;
 
-use crate::{ext::read::*, save::*, MAGIC_BYTES};
+use crate::{compression, ext::read::*, save::*, MAGIC_BYTES, SAVE_VERSION};
 
 lazy_static::lazy_static! {
-    static ref DEFAULT_MATERIALS: Vec<String> = vec!["BMC_Hologram", "BMC_Plastic", "BMC_Glow", "BMC_Metallic", "BMC_Glass"].into_iter().map(|s| s.into()).collect();
+    pub(crate) static ref DEFAULT_MATERIALS: Vec<String> = vec!["BMC_Hologram", "BMC_Plastic", "BMC_Glow", "BMC_Metallic", "BMC_Glass"].into_iter().map(|s| s.into()).collect();
 }
 
 /// A read error.
@@ -22,7 +25,9 @@ pub enum ReadError {
     #[error("generic io error: {0}")]
     IoError(#[from] io::Error),
     #[error("bad magic bytes (expected 'BRS')")]
-    BadHeader,
+    BadMagic,
+    #[error("unsupported save version {found} (supports up to {supported})")]
+    UnsupportedVersion { found: u16, supported: u16 },
     #[error("invalid data in header 1")]
     InvalidDataHeader1,
     #[error("invalid data in header 2")]
@@ -31,6 +36,25 @@ pub enum ReadError {
     BadSectionReadOrder,
     #[error("invalid compressed section")]
     InvalidCompression,
+    #[error("compressed section decompresses to more than its declared size")]
+    SizeMismatch,
+    /// Raised by `brick_offsets` for a `TableSizes` of a version with no known brick layout.
+    /// `SaveReader::new` already rejects such saves with `UnsupportedVersion`.
+    #[error("no known brick layout for save version {version}")]
+    UnsupportedBrickLayout { version: u16 },
+    #[error("{section} section is truncated or corrupt: expected {expected} bytes, got {got}")]
+    CorruptSection {
+        /// The section, e.g. "header 1" or "bricks".
+        section: &'static str,
+        /// The size the section declares.
+        expected: usize,
+        /// How many bytes could be read, or decompressed, before the data ended or was
+        /// found to be invalid.
+        got: usize,
+    },
+    #[cfg(feature = "zip")]
+    #[error("zip error: {0}")]
+    ZipError(#[from] zip::result::ZipError),
 }
 
 /// A save reader, which reads data from its `reader` (a `Read + Seek`).
@@ -50,10 +74,17 @@ impl<R: Read> SaveReader<R> {
         let mut magic = [0u8; 3];
         reader.read_exact(&mut magic)?;
         if magic != MAGIC_BYTES {
-            return Err(ReadError::BadHeader);
+            return Err(ReadError::BadMagic);
         }
 
         let version = reader.read_u16::<LittleEndian>()?;
+        if version == 0 || version > SAVE_VERSION {
+            return Err(ReadError::UnsupportedVersion {
+                found: version,
+                supported: SAVE_VERSION,
+            });
+        }
+
         let game_version = if version >= 8 {
             reader.read_i32::<LittleEndian>()?
         } else {
@@ -72,14 +103,14 @@ impl<R: Read> SaveReader<R> {
 
     /// Skip the first header.
     pub fn skip_header1(&mut self) -> Result<(), ReadError> {
-        skip_compressed(&mut self.reader)?;
+        skip_compressed(&mut self.reader, "header 1")?;
         self.header1_read = true;
         Ok(())
     }
 
     /// Read the first header.
     pub fn read_header1(&mut self) -> Result<Header1, ReadError> {
-        let (mut cursor, _) = read_compressed(&mut self.reader)?;
+        let (mut cursor, _) = read_compressed(&mut self.reader, "header 1")?;
 
         // match map: a string
         let map = cursor.read_string()?;
@@ -123,6 +154,10 @@ impl<R: Read> SaveReader<R> {
             _ => return Err(ReadError::InvalidDataHeader1),
         } as u32;
 
+        // keep anything after the known fields, to write it back out
+        let mut raw_extra = vec![];
+        cursor.read_to_end(&mut raw_extra)?;
+
         self.header1_read = true;
         Ok(Header1 {
             map,
@@ -134,12 +169,13 @@ impl<R: Read> SaveReader<R> {
             host,
             save_time: save_time.unwrap_or([0u8; 8]),
             brick_count,
+            raw_extra,
         })
     }
 
     /// Skip the second header.
     pub fn skip_header2(&mut self) -> Result<(), ReadError> {
-        skip_compressed(&mut self.reader)?;
+        skip_compressed(&mut self.reader, "header 2")?;
         self.header2_read = true;
         Ok(())
     }
@@ -150,16 +186,19 @@ impl<R: Read> SaveReader<R> {
             return Err(ReadError::BadSectionReadOrder);
         }
 
-        let (mut cursor, _) = read_compressed(&mut self.reader)?;
+        let (mut cursor, _) = read_compressed(&mut self.reader, "header 2")?;
+
+        // `ReadExt::read_array` is called by its full path, as `Read` may gain a method of the
+        // same name
 
         // match mods: an array of strings
-        let mods = cursor.read_array(|r| r.read_string())?;
+        let mods = ReadExt::read_array(&mut cursor, |r| r.read_string())?;
 
         // match brick assets: an array of strings
-        let brick_assets = cursor.read_array(|r| r.read_string())?;
+        let brick_assets = ReadExt::read_array(&mut cursor, |r| r.read_string())?;
 
         // match colors: an array of 4 bytes each, BGRA
-        let colors = cursor.read_array(|r| -> io::Result<Color> {
+        let colors = ReadExt::read_array(&mut cursor, |r| -> io::Result<Color> {
             let mut bytes = [0u8; 4];
             r.read_exact(&mut bytes)?;
             Ok(Color::from_bytes_bgra(bytes))
@@ -169,7 +208,7 @@ impl<R: Read> SaveReader<R> {
         // version >= 2: an array of strings
         //         else: a list of default materials (see top of file)
         let materials = match self.version {
-            _ if self.version >= 2 => cursor.read_array(|r| r.read_string())?,
+            _ if self.version >= 2 => ReadExt::read_array(&mut cursor, |r| r.read_string())?,
             _ => DEFAULT_MATERIALS.clone(),
         };
 
@@ -178,21 +217,23 @@ impl<R: Read> SaveReader<R> {
         //               version >= 8: a user (uuid followed by string), then an i32 for brick count
         //                       else: a user (uuid followed by string)
         let brick_owners = match self.version {
-            _ if self.version >= 3 => cursor.read_array(|r| -> io::Result<BrickOwner> {
-                match self.version {
-                    _ if self.version >= 8 => {
-                        let id = r.read_uuid()?;
-                        let name = r.read_string()?;
-                        let bricks = r.read_i32::<LittleEndian>()? as u32;
-                        Ok(BrickOwner { name, id, bricks })
-                    }
-                    _ => {
-                        let id = r.read_uuid()?;
-                        let name = r.read_string()?;
-                        Ok(BrickOwner::from(User { name, id }))
+            _ if self.version >= 3 => {
+                ReadExt::read_array(&mut cursor, |r| -> io::Result<BrickOwner> {
+                    match self.version {
+                        _ if self.version >= 8 => {
+                            let id = r.read_uuid()?;
+                            let name = r.read_string()?;
+                            let bricks = r.read_i32::<LittleEndian>()? as u32;
+                            Ok(BrickOwner { name, id, bricks })
+                        }
+                        _ => {
+                            let id = r.read_uuid()?;
+                            let name = r.read_string()?;
+                            Ok(BrickOwner::from(User { name, id }))
+                        }
                     }
-                }
-            })?,
+                })?
+            }
             _ => vec![],
         };
 
@@ -200,10 +241,14 @@ impl<R: Read> SaveReader<R> {
         // version >= 9: an array of strings
         //         else: not provided
         let physical_materials = match self.version {
-            _ if self.version >= 9 => cursor.read_array(|r| r.read_string())?,
+            _ if self.version >= 9 => ReadExt::read_array(&mut cursor, |r| r.read_string())?,
             _ => vec![],
         };
 
+        // keep anything after the known fields, to write it back out
+        let mut raw_extra = vec![];
+        cursor.read_to_end(&mut raw_extra)?;
+
         self.header2_read = true;
         Ok(Header2 {
             mods,
@@ -212,9 +257,20 @@ impl<R: Read> SaveReader<R> {
             materials,
             brick_owners,
             physical_materials,
+            raw_extra,
         })
     }
 
+    /// Read both headers, leaving the preview and bricks unread.
+    ///
+    /// The save version and game version are read when the reader is created, so this is
+    /// all that is needed for a save's metadata (map, author, description, brick count, mods...).
+    pub fn read_headers(&mut self) -> Result<(Header1, Header2), ReadError> {
+        let header1 = self.read_header1()?;
+        let header2 = self.read_header2()?;
+        Ok((header1, header2))
+    }
+
     /// Read the preview in the save.
     ///
     /// The preview is an `Preview`, which might not exist (Preview::None).
@@ -243,7 +299,7 @@ impl<R: Read> SaveReader<R> {
         }
 
         if self.reader.read_u8()? != 0 {
-            let len = self.reader.read_i32::<LittleEndian>()?;
+            let len = check_len(self.reader.read_i32::<LittleEndian>()?)?;
             io::copy(&mut self.reader.by_ref().take(len as u64), &mut io::sink())?;
         }
 
@@ -261,15 +317,11 @@ impl<R: Read> SaveReader<R> {
             return Err(ReadError::BadSectionReadOrder);
         }
 
-        let (cursor, len) = read_compressed(&mut self.reader)?;
+        let (cursor, len) = read_compressed(&mut self.reader, "bricks")?;
         let mut bits = BitReader::<_, bitstream_io::LittleEndian>::new(cursor);
-
-        let brick_asset_count = cmp::max(header2.brick_assets.len(), 2);
-        let material_count = cmp::max(header2.materials.len(), 2);
-        let physical_material_count = cmp::max(header2.physical_materials.len(), 2);
+        let tables = TableSizes::new(self.version, header2);
 
         let mut bricks = vec![];
-        let mut components = HashMap::new();
 
         // loop over each brick
         loop {
@@ -281,145 +333,221 @@ impl<R: Read> SaveReader<R> {
                 break;
             }
 
-            let asset_name_index = bits.read_uint(brick_asset_count as u32)?;
-
-            let size = match bits.read_bit()? {
-                true => Size::Procedural(
-                    bits.read_uint_packed()?,
-                    bits.read_uint_packed()?,
-                    bits.read_uint_packed()?,
-                ),
-                false => Size::Empty,
-            };
-
-            let position = (
-                bits.read_int_packed()?,
-                bits.read_int_packed()?,
-                bits.read_int_packed()?,
-            );
+            bricks.push(read_brick(&mut bits, &tables)?);
+        }
 
-            let orientation = bits.read_uint(24)?;
-            let direction = Direction::try_from(((orientation >> 2) % 6) as u8).unwrap();
-            let rotation = Rotation::try_from((orientation & 3) as u8).unwrap();
+        let brick_count = bricks.len();
+        let components = self.read_components(brick_count, |i, name, props| {
+            bricks[i as usize].components.insert(name.to_owned(), props);
+        })?;
 
-            let collision = match self.version {
-                _ if self.version >= 10 => Collision {
-                    player: bits.read_bit()?,
-                    weapon: bits.read_bit()?,
-                    interaction: bits.read_bit()?,
-                    tool: bits.read_bit()?,
-                },
-                _ => Collision::for_all(bits.read_bit()?),
-            };
-
-            let visibility = bits.read_bit()?;
-
-            let material_index = match self.version {
-                _ if self.version >= 8 => bits.read_uint(material_count as u32)?,
-                _ => {
-                    if bits.read_bit()? {
-                        bits.read_uint_packed()?
-                    } else {
-                        1
-                    }
-                }
-            };
-
-            let physical_index = match self.version {
-                _ if self.version >= 9 => bits.read_uint(physical_material_count as u32)?,
-                _ => 0,
-            };
-
-            let material_intensity = match self.version {
-                _ if self.version >= 9 => bits.read_uint(11)?,
-                _ => 5,
-            };
-
-            let color = match bits.read_bit()? {
-                true => match self.version {
-                    _ if self.version >= 9 => {
-                        let mut bytes = [0u8; 3];
-                        bits.read_bytes(&mut bytes)?;
-                        BrickColor::Unique(Color::from_bytes_rgb(bytes))
-                    }
-                    _ => {
-                        let mut bytes = [0u8; 4];
-                        bits.read_bytes(&mut bytes)?;
-                        BrickColor::Unique(Color::from_bytes_bgra(bytes))
-                    }
-                },
-                false => BrickColor::Index(bits.read_uint(header2.colors.len() as u32)?),
-            };
+        Ok((bricks, components))
+    }
 
-            let owner_index = if self.version >= 3 {
-                bits.read_uint_packed()?
-            } else {
-                0
-            };
-
-            let brick = Brick {
-                asset_name_index,
-                size,
-                position,
-                direction,
-                rotation,
-                collision,
-                visibility,
-                material_index,
-                physical_index,
-                material_intensity,
-                color,
-                owner_index,
-                components: HashMap::new(),
-            };
-
-            bricks.push(brick);
+    /// Read the bricks section, returning an iterator that decodes one brick at a time.
+    ///
+    /// The bricks are yielded without their components, which are left unread. If the
+    /// section ends partway through a brick, the iterator yields that error and then stops.
+    pub fn bricks_iter(
+        &mut self,
+        header1: &Header1,
+        header2: &Header2,
+    ) -> Result<BrickIter, ReadError> {
+        let section = self.read_brick_section()?;
+        Ok(BrickIter {
+            len: section.len() as u64,
+            bits: BitReader::endian(Cursor::new(section), bitstream_io::LittleEndian),
+            tables: TableSizes::new(self.version, header2),
+            remaining: header1.brick_count as usize,
+        })
+    }
+
+    /// Read at most the first `max` bricks, decompressing only as much of the bricks section
+    /// as they take up, e.g. to preview a very large save.
+    ///
+    /// Components are stored after every brick, so they are not loaded: the bricks' own
+    /// `components` are always empty. The reader is left partway through the bricks section,
+    /// and nothing more can be read from it afterward.
+    pub fn read_bricks_limited(
+        &mut self,
+        header1: &Header1,
+        header2: &Header2,
+        max: usize,
+    ) -> Result<PartialBricks, ReadError> {
+        if !self.preview_read || !self.header2_read {
+            return Err(ReadError::BadSectionReadOrder);
         }
 
-        let brick_count = cmp::max(bricks.len(), 2);
+        let (uncompressed_size, compressed_size) = compression::read_sizes(&mut self.reader)?;
+        let section: Box<dyn Read + '_> = if compressed_size == 0 {
+            Box::new(self.reader.by_ref())
+        } else {
+            let compressed = self.reader.by_ref().take(compressed_size);
+            Box::new(BufReader::new(ZlibDecoder::new(compressed)))
+        };
 
-        // components
-        if self.version >= 8 {
-            let (mut cursor, _) = read_compressed(&mut self.reader)?;
-            let len = cursor.read_i32::<LittleEndian>()?;
+        // the bit reader only reads whole bytes from the section as it needs them, so how
+        // much is left of it is known whenever the bits are byte aligned
+        let mut bits =
+            BitReader::endian(section.take(uncompressed_size), bitstream_io::LittleEndian);
+        let tables = TableSizes::new(self.version, header2);
+        let total = header1.brick_count as usize;
 
-            for _ in 0..len {
-                let name = cursor.read_string()?;
+        let mut bricks = vec![];
+        loop {
+            bits.byte_align();
+            if bricks.len() >= cmp::min(max, total) || bits.reader().unwrap().limit() == 0 {
+                break;
+            }
+
+            bricks.push(read_brick(&mut bits, &tables)?);
+        }
 
-                let mut bit_bytes = vec![0u8; cursor.read_i32::<LittleEndian>()? as usize];
-                cursor.read_exact(&mut bit_bytes)?;
-                let mut bits =
-                    BitReader::endian(Cursor::new(bit_bytes), bitstream_io::LittleEndian);
+        Ok(PartialBricks { bricks, total })
+    }
 
-                let version = bits.read_i32_le()?;
-                let brick_indices = bits.read_array(|r| r.read_uint(brick_count as u32))?;
+    /// Read the bricks section, returning an iterator over only the bricks owned by the user
+    /// with the ID `id`, decoding one at a time as `bricks_iter` does.
+    ///
+    /// Owners are looked up in `header2` once up front. If `id` owns no bricks in the save,
+    /// the iterator is empty. Errors decoding any brick are still yielded.
+    pub fn bricks_by_owner(
+        &mut self,
+        header1: &Header1,
+        header2: &Header2,
+        id: Uuid,
+    ) -> Result<impl Iterator<Item = Result<Brick, ReadError>>, ReadError> {
+        // the owner indices (see `Header2::owner`) of the owners with this ID
+        let owners = header2
+            .brick_owners
+            .iter()
+            .enumerate()
+            .filter(|(_, owner)| owner.id == id)
+            .map(|(i, _)| i as u32 + 1)
+            .collect::<Vec<_>>();
+
+        let mut bricks = self.bricks_iter(header1, header2)?;
+        if owners.is_empty() {
+            bricks.remaining = 0;
+        }
 
-                let properties = bits
-                    .read_array(|r| Ok((r.read_string()?, r.read_string()?)))?
-                    .into_iter()
-                    .collect::<Vec<_>>();
+        Ok(bricks.filter(move |brick| match brick {
+            Ok(brick) => owners.contains(&brick.owner_index),
+            Err(_) => true,
+        }))
+    }
 
-                // components for each brick
-                for &i in brick_indices.iter() {
-                    let mut props = HashMap::new();
-                    for (n, ty) in properties.iter() {
-                        props.insert(n.to_owned(), bits.read_unreal_type(ty)?);
-                    }
-                    bricks[i as usize].components.insert(name.to_owned(), props);
+    /// Read the components section, passing each brick's properties for each component to `attach`.
+    pub(crate) fn read_components(
+        &mut self,
+        brick_count: usize,
+        mut attach: impl FnMut(u32, &str, HashMap<String, UnrealValue>),
+    ) -> Result<HashMap<String, Component>, ReadError> {
+        let mut components = HashMap::new();
+        if self.version < 8 {
+            return Ok(components);
+        }
+
+        let actual_brick_count = brick_count;
+        let brick_count = cmp::max(brick_count, 2);
+
+        let (mut cursor, _) = read_compressed(&mut self.reader, "components")?;
+        let len = cursor.read_i32::<LittleEndian>()?;
+
+        for _ in 0..len {
+            let name = cursor.read_string()?;
+
+            let bit_len = check_len(cursor.read_i32::<LittleEndian>()?)?;
+            let mut bit_bytes = vec![];
+            cursor
+                .by_ref()
+                .take(bit_len as u64)
+                .read_to_end(&mut bit_bytes)?;
+            if bit_bytes.len() != bit_len {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            let mut bits = BitReader::endian(Cursor::new(bit_bytes), bitstream_io::LittleEndian);
+
+            let version = bits.read_i32_le()?;
+            let brick_indices = bits.read_array(|r| r.read_uint(brick_count as u32))?;
+
+            let properties = bits
+                .read_array(|r| Ok((r.read_string()?, r.read_string()?)))?
+                .into_iter()
+                .collect::<Vec<_>>();
+
+            // components for each brick
+            for &i in brick_indices.iter() {
+                if i as usize >= actual_brick_count {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "component brick index out of range",
+                    )
+                    .into());
                 }
 
-                components.insert(
-                    name,
-                    Component {
-                        version,
-                        brick_indices,
-                        properties: properties.into_iter().collect(),
-                    },
-                );
+                let mut props = HashMap::new();
+                for (n, ty) in properties.iter() {
+                    props.insert(n.to_owned(), bits.read_unreal_type(ty)?);
+                }
+                attach(i, &name, props);
             }
+
+            components.insert(
+                name,
+                Component {
+                    version,
+                    brick_indices,
+                    properties: properties.into_iter().collect(),
+                },
+            );
         }
 
-        Ok((bricks, components))
+        Ok(components)
+    }
+
+    /// Count the bricks carrying each component, by name, without decoding any bricks or
+    /// component properties. The bricks section is skipped over entirely.
+    ///
+    /// Like `read_bricks`, this must be called after the preview is read or skipped.
+    pub fn component_counts(&mut self) -> Result<HashMap<String, usize>, ReadError> {
+        if !self.preview_read || !self.header2_read {
+            return Err(ReadError::BadSectionReadOrder);
+        }
+
+        let mut counts = HashMap::new();
+        skip_compressed(&mut self.reader, "bricks")?;
+        if self.version < 8 {
+            return Ok(counts);
+        }
+
+        let (mut cursor, _) = read_compressed(&mut self.reader, "components")?;
+        let len = cursor.read_i32::<LittleEndian>()?;
+
+        for _ in 0..len {
+            let name = cursor.read_string()?;
+            let bit_len = check_len(cursor.read_i32::<LittleEndian>()?)?;
+            let start = cursor.position();
+
+            // the bit data starts byte aligned with the version, then the brick index count
+            let _version = cursor.read_i32::<LittleEndian>()?;
+            let count = check_len(cursor.read_i32::<LittleEndian>()?)?;
+            cursor.set_position(start + bit_len as u64);
+
+            counts.insert(name, count);
+        }
+
+        Ok(counts)
+    }
+
+    /// Read the bricks section without decoding it, returning the decompressed bitstream.
+    pub(crate) fn read_brick_section(&mut self) -> Result<Vec<u8>, ReadError> {
+        if !self.preview_read || !self.header2_read {
+            return Err(ReadError::BadSectionReadOrder);
+        }
+
+        let (cursor, _) = read_compressed(&mut self.reader, "bricks")?;
+        Ok(cursor.into_inner())
     }
 
     /// Read all parts of a save into a `SaveData`.
@@ -430,7 +558,7 @@ impl<R: Read> SaveReader<R> {
         let (bricks, components) = self.read_bricks(&header1, &header2)?;
 
         Ok(SaveData {
-            version: self.version,
+            save_version: self.version,
             game_version: self.game_version,
             header1,
             header2,
@@ -448,7 +576,7 @@ impl<R: Read> SaveReader<R> {
         let (bricks, components) = self.read_bricks(&header1, &header2)?;
 
         Ok(SaveData {
-            version: self.version,
+            save_version: self.version,
             game_version: self.game_version,
             header1,
             header2,
@@ -457,51 +585,418 @@ impl<R: Read> SaveReader<R> {
             components,
         })
     }
+
+    /// Read the headers and preview of a save, leaving its bricks to be decoded on demand.
+    /// See `LazySave`.
+    ///
+    /// Every brick is decoded once to find where it starts, so this fails if a brick can't
+    /// be decoded, like `read_bricks`.
+    pub fn read_lazy(&mut self) -> Result<LazySave, ReadError> {
+        let header1 = self.read_header1()?;
+        let header2 = self.read_header2()?;
+        let preview = self.read_preview()?;
+
+        let section = self.read_brick_section()?;
+        let tables = TableSizes::new(self.version, &header2);
+        let offsets = brick_offsets(&section, header1.brick_count as usize, &tables)?;
+
+        let mut brick_components: HashMap<u32, HashMap<String, HashMap<String, UnrealValue>>> =
+            HashMap::new();
+        let components = self.read_components(offsets.len(), |i, name, props| {
+            brick_components
+                .entry(i)
+                .or_default()
+                .insert(name.to_owned(), props);
+        })?;
+
+        Ok(LazySave {
+            save_version: self.version,
+            game_version: self.game_version,
+            header1,
+            header2,
+            preview,
+            components,
+            section,
+            offsets,
+            tables,
+            brick_components,
+            cache: RefCell::new(HashMap::new()),
+        })
+    }
 }
 
-/// Read a compressed section from a `Read`, following the BRS spec for compressed sections.
-fn read_compressed(reader: &mut impl Read) -> Result<(Cursor<Vec<u8>>, i32), ReadError> {
-    let (uncompressed_size, compressed_size) = (
-        reader.read_i32::<LittleEndian>()?,
-        reader.read_i32::<LittleEndian>()?,
-    );
-    if uncompressed_size < 0 || compressed_size < 0 || compressed_size >= uncompressed_size {
-        return Err(ReadError::InvalidCompression);
+impl SaveReader<BufReader<File>> {
+    /// Create a new save reader that reads the file at `path` through a `BufReader`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ReadError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(feature = "zip")]
+impl SaveReader<Cursor<Vec<u8>>> {
+    /// Create a new save reader from the entry named `entry_name` in the zip archive at `path`.
+    ///
+    /// The entry is decompressed into memory up front.
+    pub fn from_zip(path: impl AsRef<Path>, entry_name: &str) -> Result<Self, ReadError> {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        let mut entry = archive.by_name(entry_name)?;
+
+        // the entry's declared size can't be trusted, so the buffer grows with what is read
+        let mut bytes = vec![];
+        entry.read_to_end(&mut bytes)?;
+        Self::new(Cursor::new(bytes))
     }
+}
+
+/// List the names of the `.brs` entries in the zip archive at `path`, sorted by name.
+#[cfg(feature = "zip")]
+pub fn list_saves(path: impl AsRef<Path>) -> Result<Vec<String>, ReadError> {
+    let archive = zip::ZipArchive::new(File::open(path)?)?;
+    let mut names = archive
+        .file_names()
+        .filter(|name| name.to_lowercase().ends_with(".brs"))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names)
+}
 
-    let mut bytes = vec![0u8; uncompressed_size as usize];
+/// The first bricks of a save, without their components. Created by
+/// `SaveReader::read_bricks_limited`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialBricks {
+    /// The bricks read, in order. Their `components` are empty, as components aren't loaded.
+    pub bricks: Vec<Brick>,
 
-    if compressed_size == 0 {
-        // no need to decompress first
-        reader.read_exact(&mut bytes)?;
-    } else {
-        // decompress first, then read
-        let mut compressed = vec![0u8; compressed_size as usize];
-        reader.read_exact(&mut compressed)?;
-        ZlibDecoder::new(&compressed[..]).read_exact(&mut bytes)?;
+    /// The number of bricks in the save, which may be more than were read.
+    pub total: usize,
+}
+
+impl PartialBricks {
+    /// Whether the save has bricks that weren't read.
+    pub fn is_truncated(&self) -> bool {
+        self.bricks.len() < self.total
     }
+}
 
-    Ok((Cursor::new(bytes), uncompressed_size))
+/// An iterator over the bricks of a save, decoding one at a time. Created by
+/// `SaveReader::bricks_iter`.
+pub struct BrickIter {
+    bits: BitReader<Cursor<Vec<u8>>, bitstream_io::LittleEndian>,
+    len: u64,
+    tables: TableSizes,
+    remaining: usize,
 }
 
-/// Read a compressed section from a `Read`, discarding its contents.
-fn skip_compressed(reader: &mut impl Read) -> Result<(), ReadError> {
-    let (uncompressed_size, compressed_size) = (
-        reader.read_i32::<LittleEndian>()?,
-        reader.read_i32::<LittleEndian>()?,
-    );
-    if uncompressed_size < 0 || compressed_size < 0 || compressed_size >= uncompressed_size {
-        return Err(ReadError::InvalidCompression);
+impl Iterator for BrickIter {
+    type Item = Result<Brick, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // bricks start on a byte boundary, just as they are written
+        self.bits.byte_align();
+        if self.remaining == 0 || self.bits.reader().unwrap().position() >= self.len {
+            return None;
+        }
+
+        match read_brick(&mut self.bits, &self.tables) {
+            Ok(brick) => {
+                self.remaining -= 1;
+                Some(Ok(brick))
+            }
+            Err(e) => {
+                self.remaining = 0;
+                Some(Err(e))
+            }
+        }
     }
+}
 
-    io::copy(
-        &mut reader.take(if compressed_size == 0 {
-            uncompressed_size as u64
-        } else {
-            compressed_size as u64
-        }),
-        &mut io::sink(),
-    )?;
+/// A save whose headers, preview and components have been read, but whose bricks are only
+/// decoded when asked for. Created by `SaveReader::read_lazy`.
+///
+/// Bricks start on byte boundaries, so the reader makes one pass over the brick section
+/// to record where each brick starts. `brick` then decodes a single brick from its offset,
+/// caching it for later calls.
+pub struct LazySave {
+    pub save_version: u16,
+    pub game_version: i32,
+    pub header1: Header1,
+    pub header2: Header2,
+    pub preview: Preview,
+    pub components: HashMap<String, Component>,
+
+    section: Vec<u8>,
+    offsets: Vec<usize>,
+    tables: TableSizes,
+    brick_components: HashMap<u32, HashMap<String, HashMap<String, UnrealValue>>>,
+    cache: RefCell<HashMap<usize, Brick>>,
+}
+
+impl LazySave {
+    /// The number of bricks in the save.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether the save has no bricks.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Decode the brick at `index`, along with its components, or `None` if out of range.
+    pub fn brick(&self, index: usize) -> Option<Brick> {
+        if let Some(brick) = self.cache.borrow().get(&index) {
+            return Some(brick.clone());
+        }
+
+        let offset = *self.offsets.get(index)?;
+        let mut bits = BitReader::endian(&self.section[offset..], bitstream_io::LittleEndian);
+        // every indexed brick was already decoded once while building the offsets
+        let mut brick =
+            read_brick(&mut bits, &self.tables).expect("indexed brick failed to decode");
+        if let Some(components) = self.brick_components.get(&(index as u32)) {
+            brick.components = components.clone();
+        }
+
+        self.cache.borrow_mut().insert(index, brick.clone());
+        Some(brick)
+    }
+}
+
+/// Find the byte offset of each brick in a decompressed brick section, stopping after
+/// `count` bricks or at the end of the section.
+///
+/// Each brick starts on a byte boundary, but bricks vary in length, so every brick is
+/// decoded once to find where the next one starts. Fails if any brick fails to decode,
+/// as `SaveReader::read_bricks` would, or with `ReadError::UnsupportedBrickLayout` if
+/// `tables.version` has no known brick layout, even if the section is empty.
+pub fn brick_offsets(
+    section: &[u8],
+    count: usize,
+    tables: &TableSizes,
+) -> Result<Vec<usize>, ReadError> {
+    check_brick_layout(tables.version)?;
+
+    let mut bits = BitReader::endian(Cursor::new(section), bitstream_io::LittleEndian);
+    let mut offsets = vec![];
+
+    loop {
+        bits.byte_align();
+        let position = bits.reader().unwrap().position() as usize;
+        if offsets.len() >= count || position >= section.len() {
+            break;
+        }
+
+        read_brick(&mut bits, tables)?;
+        offsets.push(position);
+    }
+
+    Ok(offsets)
+}
+
+/// The save version and header 2 table lengths that determine how bricks are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableSizes {
+    /// The save version.
+    pub version: u16,
+
+    /// The length of `Header2`'s `brick_assets`.
+    pub brick_assets: usize,
+
+    /// The length of `Header2`'s `materials`.
+    pub materials: usize,
+
+    /// The length of `Header2`'s `physical_materials`.
+    pub physical_materials: usize,
+
+    /// The length of `Header2`'s `colors`.
+    pub colors: usize,
+}
+
+impl TableSizes {
+    /// Get the table sizes of a save of `version` with the second header `header2`.
+    pub fn new(version: u16, header2: &Header2) -> Self {
+        TableSizes {
+            version,
+            brick_assets: header2.brick_assets.len(),
+            materials: header2.materials.len(),
+            physical_materials: header2.physical_materials.len(),
+            colors: header2.colors.len(),
+        }
+    }
+}
+
+/// Fail with `ReadError::UnsupportedBrickLayout` if bricks of save `version` can't be read.
+fn check_brick_layout(version: u16) -> Result<(), ReadError> {
+    if (1..=SAVE_VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(ReadError::UnsupportedBrickLayout { version })
+    }
+}
+
+/// Read a single brick (without its components) from a bitstream positioned at its start.
+///
+/// The layout depends on the save version: owners are stored from version 3, material
+/// indices have a fixed width from version 8, physical materials, material intensities and
+/// RGB colors (rather than BGRA) are stored from version 9, and collision is split into
+/// four flags from version 10. Other versions fail with `ReadError::UnsupportedBrickLayout`.
+pub(crate) fn read_brick(bits: &mut impl BitRead, tables: &TableSizes) -> Result<Brick, ReadError> {
+    let version = tables.version;
+    check_brick_layout(version)?;
+
+    let brick_asset_count = cmp::max(tables.brick_assets, 2);
+    let material_count = cmp::max(tables.materials, 2);
+    let physical_material_count = cmp::max(tables.physical_materials, 2);
+    let color_count = cmp::max(tables.colors, 2);
+
+    let asset_name_index = bits.read_uint(brick_asset_count as u32)?;
+
+    let size = match bits.read_bit()? {
+        true => Size::Procedural(
+            bits.read_uint_packed()?,
+            bits.read_uint_packed()?,
+            bits.read_uint_packed()?,
+        ),
+        false => Size::Empty,
+    };
+
+    let position = (
+        bits.read_int_packed()?,
+        bits.read_int_packed()?,
+        bits.read_int_packed()?,
+    );
+
+    let Orientation {
+        direction,
+        rotation,
+    } = Orientation::from_packed(bits.read_uint(24)?);
+
+    let collision = match version {
+        _ if version >= 10 => Collision {
+            player: bits.read_bit()?,
+            weapon: bits.read_bit()?,
+            interaction: bits.read_bit()?,
+            tool: bits.read_bit()?,
+        },
+        _ => Collision::for_all(bits.read_bit()?),
+    };
+
+    let visibility = bits.read_bit()?;
+
+    let material_index = match version {
+        _ if version >= 8 => bits.read_uint(material_count as u32)?,
+        _ => {
+            if bits.read_bit()? {
+                bits.read_uint_packed()?
+            } else {
+                1
+            }
+        }
+    };
+
+    let physical_index = match version {
+        _ if version >= 9 => bits.read_uint(physical_material_count as u32)?,
+        _ => 0,
+    };
+
+    let material_intensity = match version {
+        _ if version >= 9 => bits.read_uint(11)?,
+        _ => 5,
+    };
+
+    let color = match bits.read_bit()? {
+        true => match version {
+            _ if version >= 9 => {
+                let mut bytes = [0u8; 3];
+                bits.read_bytes(&mut bytes)?;
+                BrickColor::Unique(Color::from_bytes_rgb(bytes))
+            }
+            _ => {
+                let mut bytes = [0u8; 4];
+                bits.read_bytes(&mut bytes)?;
+                BrickColor::Unique(Color::from_bytes_bgra(bytes))
+            }
+        },
+        false => BrickColor::Index(bits.read_uint(color_count as u32)?),
+    };
+
+    let owner_index = if version >= 3 {
+        bits.read_uint_packed()?
+    } else {
+        0
+    };
+
+    Ok(Brick {
+        asset_name_index,
+        size,
+        position,
+        direction,
+        rotation,
+        collision,
+        visibility,
+        material_index,
+        physical_index,
+        material_intensity,
+        color,
+        owner_index,
+        components: HashMap::new(),
+    })
+}
+
+/// Read the compressed `section` from a `Read` into a cursor, along with its length.
+fn read_compressed(
+    reader: &mut impl Read,
+    section: &'static str,
+) -> Result<(Cursor<Vec<u8>>, i32), ReadError> {
+    let bytes = compression::read_section(reader, section)?;
+    let len = bytes.len() as i32;
+    Ok((Cursor::new(bytes), len))
+}
+
+/// Read the compressed `section` from a `Read`, discarding its contents.
+fn skip_compressed(reader: &mut impl Read, section: &'static str) -> Result<(), ReadError> {
+    let (uncompressed_size, compressed_size) = compression::read_sizes(reader)?;
+    let expected = if compressed_size == 0 {
+        uncompressed_size
+    } else {
+        compressed_size
+    };
+    let got = io::copy(&mut reader.take(expected), &mut io::sink())?;
+    if got != expected {
+        return Err(ReadError::CorruptSection {
+            section,
+            expected: expected as usize,
+            got: got as usize,
+        });
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use bitstream_io::{BitReader, LittleEndian};
+
+    use super::{read_brick, ReadError, TableSizes};
+    use crate::{save::Header2, SAVE_VERSION};
+
+    #[test]
+    fn unsupported_brick_layout() {
+        let bytes = [0u8; 64];
+        for version in [0, SAVE_VERSION + 1, u16::MAX] {
+            let tables = TableSizes::new(version, &Header2::default());
+            let mut bits = BitReader::endian(&bytes[..], LittleEndian);
+            assert!(matches!(
+                read_brick(&mut bits, &tables),
+                Err(ReadError::UnsupportedBrickLayout { version: v }) if v == version
+            ));
+        }
+
+        for version in 1..=SAVE_VERSION {
+            let tables = TableSizes::new(version, &Header2::default());
+            let mut bits = BitReader::endian(&bytes[..], LittleEndian);
+            assert!(read_brick(&mut bits, &tables).is_ok());
+        }
+    }
+}
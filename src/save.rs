@@ -1,6 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::{Infallible, TryFrom};
+use std::fmt;
+use std::fs::{self, File};
 use std::hash::{Hash, Hasher};
-use std::io::Read;
+use std::io::{BufWriter, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -14,18 +20,35 @@ use {
         Deserialize, Deserializer, Serialize, Serializer,
     },
     serde_repr::{Deserialize_repr, Serialize_repr},
-    std::fmt,
 };
 
-use crate::read::ReadError;
+use thiserror::Error;
+#[cfg(feature = "image")]
+use {image::DynamicImage, image::ImageFormat};
+
+use crate::read::{ReadError, SaveReader};
+use crate::validate::{FeatureTable, Incompatibility, ValidationError};
+use crate::write::{SaveWriter, SizeEstimate, WriteError};
 use crate::SAVE_VERSION;
 
+/// The prefix of the component names used to store brick groups. See `SaveData::groups`.
+pub const GROUP_COMPONENT_PREFIX: &str = "BRS_Group:";
+
+/// The highest material intensity the game uses. Intensities are stored in 11 bits, so
+/// values up to 2047 can be read, but only 0 through this are meaningful.
+pub const MAX_MATERIAL_INTENSITY: u32 = 10;
+
+/// The minimum and maximum corners of a box, in game units.
+pub type Bounds = ((i32, i32, i32), (i32, i32, i32));
+
 /// Every part of a save file.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(default))]
 pub struct SaveData {
-    /// The version of the save. Only relevant for reads; this automatically uses `SAVE_VERSION` when writing.
-    pub version: u16,
+    /// The version of the save format the save was read from. Only relevant for reads; this
+    /// automatically uses `SAVE_VERSION` when writing.
+    #[cfg_attr(feature = "serialize", serde(alias = "version"))]
+    pub save_version: u16,
 
     /// The game version the save was saved on.
     pub game_version: i32,
@@ -38,7 +61,7 @@ pub struct SaveData {
     #[cfg_attr(feature = "serialize", serde(flatten))]
     pub header2: Header2,
 
-    /// The preview of the save, if any.
+    /// The preview of the save, if any. Not serialized.
     #[cfg_attr(feature = "serialize", serde(skip))]
     pub preview: Preview,
 
@@ -55,12 +78,725 @@ impl SaveData {
     pub fn into_octree(self) -> crate::util::octree::SaveOctree {
         crate::util::octree::SaveOctree::new(self)
     }
+
+    /// Estimate how expensive this save will be to load and render. See `PerfScore`.
+    #[cfg(feature = "util")]
+    pub fn performance_score(&self) -> crate::util::perf::PerfScore {
+        crate::util::perf::PerfScore::new(self)
+    }
+
+    /// Convert every brick with a `BrickColor::Unique` color to a `BrickColor::Index` into the palette.
+    ///
+    /// Existing palette entries are kept. The unique colors are added to the palette, and if that
+    /// would make it larger than `max_colors`, they are first quantized (with median cut) into as
+    /// many colors as fit. Each brick is then given the nearest palette color.
+    #[cfg(feature = "util")]
+    pub fn palettize(&mut self, max_colors: usize) -> crate::util::palette::PalettizeReport {
+        crate::util::palette::palettize(self, max_colors)
+    }
+
+    /// Quantize `image` to a palette of at most `max_colors` colors (with median cut), and find
+    /// the nearest palette color for each pixel.
+    ///
+    /// The palette can be used as `Header2::colors` and the indices as `BrickColor::Index`,
+    /// e.g. to build a mosaic with one brick per pixel.
+    #[cfg(all(feature = "util", feature = "image"))]
+    pub fn palette_from_image(
+        image: &image::RgbaImage,
+        max_colors: usize,
+    ) -> crate::util::palette::ImagePalette {
+        crate::util::palette::palette_from_image(image, max_colors)
+    }
+
+    /// Merge adjacent procedural bricks into larger ones (greedy meshing), returning how
+    /// many bricks were eliminated.
+    ///
+    /// Only bricks of the same size that line up on a grid, and that match in everything
+    /// but position (asset, orientation, color, material, collision, owner, ...), are merged.
+    /// Only plain boxes (`util::BOX_ASSETS`) are merged, as merging e.g. ramps or wedges
+    /// would change their shape, and bricks with components are left alone. Merged bricks
+    /// are no larger than `util::MAX_PROCEDURAL_SIZE` along any axis, and take the place of
+    /// the first brick merged into them. The owners' brick counts are updated to match.
+    #[cfg(feature = "util")]
+    pub fn greedy_merge(&mut self) -> usize {
+        crate::util::merge::greedy_merge(self)
+    }
+
+    /// Split this save into horizontal slabs `band_height` units tall, from the bottom up.
+    ///
+    /// Each slab is a standalone save holding only the table entries and components its
+    /// bricks use. A brick spanning slabs goes into the slab containing its base. Slabs
+    /// with no bricks are skipped.
+    ///
+    /// Panics if `band_height` is not positive.
+    #[cfg(feature = "util")]
+    pub fn split_by_z(&self, band_height: i32) -> Vec<SaveData> {
+        crate::util::split::split_by_z(self, band_height)
+    }
+
+    /// Get the minimum and maximum corners of the box containing every brick, or `None` if
+    /// there are no bricks.
+    ///
+    /// Procedural sizes are half extents along the brick's own axes, so each brick spans
+    /// `position - size` to `position + size` once its size is rotated into world axes.
+    /// Non-procedural bricks use their asset's size, or are treated as a point at their
+    /// position if the asset's size is not known.
+    #[cfg(feature = "util")]
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bricks
+            .iter()
+            .map(|brick| crate::util::get_brick_bounds(brick, &self.header2.brick_assets))
+            .reduce(|(amin, amax), (bmin, bmax)| {
+                (
+                    (amin.0.min(bmin.0), amin.1.min(bmin.1), amin.2.min(bmin.2)),
+                    (amax.0.max(bmax.0), amax.1.max(bmax.1), amax.2.max(bmax.2)),
+                )
+            })
+    }
+
+    /// Move the bricks and components of `other` into this save.
+    ///
+    /// The brick assets, materials, physical materials, colors and owners `other`'s bricks use
+    /// are added to this save's `Header2` where they are not already present (owners are
+    /// matched by ID), and the appended bricks' indices are remapped to them. Entries no brick
+    /// uses are not added. Indices out of range of `other`'s tables are left unchanged.
+    ///
+    /// Components with the same name are merged, keeping this save's version and adding
+    /// `other`'s properties. Bricks missing a merged property are given the default value of
+    /// its type (see `UnrealValue::default_for`), so the merged save can be written.
+    pub fn append(&mut self, other: SaveData) {
+        fn remap<T: Clone + PartialEq>(into: &mut Vec<T>, from: &[T], used: &[bool]) -> Vec<u32> {
+            from.iter()
+                .zip(used)
+                .map(
+                    |(entry, &used)| match into.iter().position(|e| e == entry) {
+                        Some(i) => i as u32,
+                        None if used => {
+                            into.push(entry.clone());
+                            into.len() as u32 - 1
+                        }
+                        // never looked up
+                        None => u32::MAX,
+                    },
+                )
+                .collect()
+        }
+
+        let mut assets = vec![false; other.header2.brick_assets.len()];
+        let mut materials = vec![false; other.header2.materials.len()];
+        let mut physical_materials = vec![false; other.header2.physical_materials.len()];
+        let mut colors = vec![false; other.header2.colors.len()];
+        let mut owners = vec![false; other.header2.brick_owners.len()];
+
+        let mark = |used: &mut [bool], index: u32| {
+            if let Some(used) = used.get_mut(index as usize) {
+                *used = true;
+            }
+        };
+        for brick in other.bricks.iter() {
+            mark(&mut assets, brick.asset_name_index);
+            mark(&mut materials, brick.material_index);
+            mark(&mut physical_materials, brick.physical_index);
+            if let BrickColor::Index(index) = brick.color {
+                mark(&mut colors, index);
+            }
+            if let Some(i) = owner_position(brick.owner_index) {
+                mark(&mut owners, i as u32);
+            }
+        }
+
+        let header2 = &mut self.header2;
+        let assets = remap(
+            &mut header2.brick_assets,
+            &other.header2.brick_assets,
+            &assets,
+        );
+        let materials = remap(&mut header2.materials, &other.header2.materials, &materials);
+        let physical_materials = remap(
+            &mut header2.physical_materials,
+            &other.header2.physical_materials,
+            &physical_materials,
+        );
+        let colors = remap(&mut header2.colors, &other.header2.colors, &colors);
+
+        let owners = other
+            .header2
+            .brick_owners
+            .iter()
+            .zip(owners)
+            .map(|(owner, used)| {
+                if !used {
+                    // never looked up
+                    return u32::MAX;
+                }
+                match header2
+                    .brick_owners
+                    .iter_mut()
+                    .position(|o| o.id == owner.id)
+                {
+                    Some(i) => {
+                        header2.brick_owners[i].bricks += owner.bricks;
+                        i as u32 + 1
+                    }
+                    None => {
+                        header2.brick_owners.push(owner.clone());
+                        header2.brick_owners.len() as u32
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let lookup =
+            |table: &[u32], index: u32| table.get(index as usize).copied().unwrap_or(index);
+
+        let offset = self.bricks.len() as u32;
+        self.bricks.reserve(other.bricks.len());
+        for mut brick in other.bricks {
+            brick.asset_name_index = lookup(&assets, brick.asset_name_index);
+            brick.material_index = lookup(&materials, brick.material_index);
+            brick.physical_index = lookup(&physical_materials, brick.physical_index);
+            if let BrickColor::Index(index) = &mut brick.color {
+                *index = lookup(&colors, *index);
+            }
+            if let Some(i) = owner_position(brick.owner_index) {
+                brick.owner_index = lookup(&owners, i as u32) + 1;
+            }
+            self.bricks.push(brick);
+        }
+
+        // components described by both saves, whose bricks may be missing properties
+        let mut merged = vec![];
+        for (name, component) in other.components {
+            let indices = component.brick_indices.iter().map(|i| i + offset);
+            match self.components.get_mut(&name) {
+                Some(existing) => {
+                    existing.brick_indices.extend(indices);
+                    if existing.properties != component.properties {
+                        for (property, ty) in component.properties {
+                            existing.properties.entry(property).or_insert(ty);
+                        }
+                        merged.push(name);
+                    }
+                }
+                None => {
+                    let brick_indices = indices.collect();
+                    self.components.insert(
+                        name,
+                        Component {
+                            brick_indices,
+                            ..component
+                        },
+                    );
+                }
+            }
+        }
+
+        for name in merged {
+            let properties = &self.components[&name].properties;
+            for brick in self.bricks.iter_mut() {
+                if let Some(values) = brick.components.get_mut(&name) {
+                    for (property, ty) in properties.iter() {
+                        if !values.contains_key(property) {
+                            if let Some(value) = UnrealValue::default_for(ty) {
+                                values.insert(property.clone(), value);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.header1.brick_count = self.bricks.len() as u32;
+    }
+
+    /// Move every brick by `offset`.
+    ///
+    /// Positions wrap around on overflow, so translating by an offset and then by its
+    /// negation always gives back the original positions. Note that a coordinate of
+    /// `i32::MIN` cannot be written.
+    pub fn translate(&mut self, offset: (i32, i32, i32)) {
+        for brick in self.bricks.iter_mut() {
+            let p = &mut brick.position;
+            *p = (
+                p.0.wrapping_add(offset.0),
+                p.1.wrapping_add(offset.1),
+                p.2.wrapping_add(offset.2),
+            );
+        }
+    }
+
+    /// Rotate the whole build about the Z axis through the origin by `quarter_turns` quarter
+    /// turns, counterclockwise when viewed from above (so +X turns toward +Y).
+    ///
+    /// Each brick's position and orientation are rotated; sizes are relative to the brick's
+    /// orientation, so they are unchanged. Like `translate`, positions wrap around on overflow.
+    #[cfg(feature = "util")]
+    pub fn rotate(&mut self, quarter_turns: u8) {
+        let turns = quarter_turns % 4;
+        for brick in self.bricks.iter_mut() {
+            for _ in 0..turns {
+                let (x, y, z) = brick.position;
+                brick.position = (y.wrapping_neg(), x, z);
+            }
+            brick.set_orientation(crate::util::rotate_orientation_z(
+                brick.orientation(),
+                turns,
+            ));
+        }
+    }
+
+    /// Convert this `SaveData` into a `SharedSave`, which is cheap to clone and only copies
+    /// the parts that are mutated.
+    pub fn into_shared(self) -> crate::shared::SharedSave {
+        crate::shared::SharedSave::new(self)
+    }
+
+    /// Create a save with one procedural brick per filled cell of `grid`, using a palette of
+    /// the grid's distinct colors. See `VoxelGrid` for how cells map to world positions.
+    ///
+    /// Call `greedy_merge` on the result to merge neighboring cells of the same color.
+    #[cfg(feature = "util")]
+    pub fn from_voxel_grid(grid: &crate::util::voxel::VoxelGrid) -> Self {
+        crate::util::voxel::from_voxel_grid(grid)
+    }
+
+    /// Split this save's bricks into columns. See `BrickColumns`.
+    pub fn to_columns(&self) -> BrickColumns {
+        BrickColumns::from_bricks(&self.bricks)
+    }
+
+    /// Create a save with default headers whose bricks are joined from `columns`.
+    ///
+    /// Panics if the columns are not all the same length.
+    pub fn from_columns(columns: BrickColumns) -> Self {
+        let bricks = columns.into_bricks();
+        SaveData {
+            header1: Header1 {
+                brick_count: bricks.len() as u32,
+                ..Default::default()
+            },
+            bricks,
+            ..Default::default()
+        }
+    }
+
+    /// Count the bricks using each material, by material name.
+    ///
+    /// Bricks whose material index is out of range of `Header2`'s `materials` are not counted.
+    pub fn material_usage(&self) -> HashMap<&str, usize> {
+        let mut usage = HashMap::new();
+        for brick in self.bricks.iter() {
+            if let Some(material) = self.header2.materials.get(brick.material_index as usize) {
+                *usage.entry(material.as_str()).or_insert(0) += 1;
+            }
+        }
+        usage
+    }
+
+    /// Check that every brick's asset, material, physical material, color and owner indices
+    /// refer to entries in `Header2`, returning the first brick and field that does not.
+    /// Physical material indices aren't checked for saves before version 9, which have no
+    /// physical materials, or when `Header2::physical_materials` is empty.
+    ///
+    /// Each brick's component values are also checked: every component must be described in
+    /// `components`, every property must be present with the described type, and floats
+    /// must be finite, as the game can't load infinite or NaN values.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        crate::validate::validate(self)
+    }
+
+    /// Check the materials, brick assets and components used by this save against `table`,
+    /// reporting those introduced after `game_version`.
+    ///
+    /// This crate does not know when each feature was added to the game, so there is no
+    /// built-in table; the caller has to supply one.
+    pub fn validate_with_table(
+        &self,
+        game_version: i32,
+        table: &FeatureTable,
+    ) -> Vec<Incompatibility> {
+        table.check(self, game_version)
+    }
+
+    /// Encode this save without writing it, returning the uncompressed size of each section.
+    ///
+    /// Fails where writing would, e.g. when a brick is missing a component property.
+    pub fn estimate_size(&self) -> Result<SizeEstimate, WriteError> {
+        crate::write::estimate_size(self, SAVE_VERSION)
+    }
+
+    /// Hash the save's bricks and components, as they are encoded before compression.
+    ///
+    /// The hash doesn't depend on compression or on `Header1`, and is stable across versions
+    /// of this library that encode the save the same way, so it can be used to tell whether
+    /// two saves have the same bricks. It does depend on the lengths of `Header2`'s asset,
+    /// material, physical material and color tables, as they set how many bits the bricks'
+    /// indices are encoded in. Their contents aren't hashed, though: bricks refer to them by
+    /// index, so saves whose tables hold different entries can still share a hash; compare
+    /// those separately if they matter.
+    ///
+    /// Fails where writing would, e.g. when a brick is missing a component property.
+    pub fn content_hash(&self) -> Result<u64, WriteError> {
+        crate::write::content_hash(self)
+    }
+
+    /// Read a save from the bytes of a save file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<SaveData, ReadError> {
+        SaveReader::new(Cursor::new(bytes))?.read_all()
+    }
+
+    /// Write this save to the bytes of a save file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, WriteError> {
+        let mut bytes = vec![];
+        SaveWriter::new(&mut bytes, self).write()?;
+        Ok(bytes)
+    }
+
+    /// Write this save to `path` without ever leaving a partially written file there.
+    ///
+    /// The save is written to `path` with `.tmp` appended, synced to disk, and then renamed
+    /// over `path`. The temporary file sits next to `path` so the rename never crosses
+    /// filesystems. If anything fails, the temporary file is removed and `path` is untouched.
+    pub fn save_to_path_atomic(&self, path: impl AsRef<Path>) -> Result<(), WriteError> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let result = (|| -> Result<(), WriteError> {
+            let mut writer = BufWriter::new(File::create(&tmp)?);
+            SaveWriter::new(&mut writer, self).write()?;
+            writer
+                .into_inner()
+                .map_err(|e| e.into_error())?
+                .sync_all()?;
+            fs::rename(&tmp, path)?;
+
+            // make the rename itself durable
+            #[cfg(unix)]
+            if let Some(parent) = path.parent() {
+                let parent = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+                File::open(parent)?.sync_all()?;
+            }
+
+            Ok(())
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    /// Rebuild every component's `brick_indices` from the bricks that actually carry it.
+    ///
+    /// The writer always derives the indices from the bricks, so this only brings the
+    /// in-memory `components` map back in line with them, e.g. after removing bricks.
+    /// Components no brick carries are left with no indices (and are not written).
+    pub fn reconcile_components(&mut self) {
+        for component in self.components.values_mut() {
+            component.brick_indices.clear();
+        }
+
+        for (i, brick) in self.bricks.iter().enumerate() {
+            for name in brick.components.keys() {
+                if let Some(component) = self.components.get_mut(name) {
+                    component.brick_indices.push(i as u32);
+                }
+            }
+        }
+    }
+
+    /// Keep only the bricks for which `f` returns true, keeping the rest of the save consistent
+    /// with them: `brick_count` and the components' `brick_indices` are updated (see
+    /// `reconcile_components`), and the owners' brick counts are recounted (see
+    /// `recompute_owners`, though bricks with an invalid owner are just not counted).
+    ///
+    /// Table entries no remaining brick uses are kept, see `compact_tables` and
+    /// `prune_owners` to remove them.
+    pub fn retain_bricks<F: FnMut(&Brick) -> bool>(&mut self, f: F) {
+        self.bricks.retain(f);
+        self.header1.brick_count = self.bricks.len() as u32;
+        self.reconcile_components();
+        self.recount_owners();
+    }
+
+    /// Recount the bricks owned by each of `Header2`'s `brick_owners`, like
+    /// `recompute_owners`, but not counting bricks whose owner is out of range towards any
+    /// owner.
+    pub(crate) fn recount_owners(&mut self) {
+        for owner in self.header2.brick_owners.iter_mut() {
+            owner.bricks = 0;
+        }
+        for brick in self.bricks.iter() {
+            if let Some(owner) = self.header2.owner_mut(brick.owner_index) {
+                owner.bricks += 1;
+            }
+        }
+    }
+
+    /// Remove the brick assets, colors, materials and physical materials in `Header2` that no
+    /// brick uses, updating the bricks' indices to match. Returns how many entries were removed.
+    ///
+    /// Entries keep their order. Indices out of range of their table are left unchanged.
+    /// Owners are not touched, see `prune_owners`.
+    pub fn compact_tables(&mut self) -> usize {
+        let header2 = &mut self.header2;
+        let mut assets = vec![false; header2.brick_assets.len()];
+        let mut colors = vec![false; header2.colors.len()];
+        let mut materials = vec![false; header2.materials.len()];
+        let mut physical_materials = vec![false; header2.physical_materials.len()];
+
+        let mark = |used: &mut [bool], index: u32| {
+            if let Some(used) = used.get_mut(index as usize) {
+                *used = true;
+            }
+        };
+        for brick in self.bricks.iter() {
+            mark(&mut assets, brick.asset_name_index);
+            mark(&mut materials, brick.material_index);
+            mark(&mut physical_materials, brick.physical_index);
+            if let BrickColor::Index(index) = brick.color {
+                mark(&mut colors, index);
+            }
+        }
+
+        let before = header2.brick_assets.len()
+            + header2.colors.len()
+            + header2.materials.len()
+            + header2.physical_materials.len();
+        let assets = compact(&mut header2.brick_assets, &assets);
+        let colors = compact(&mut header2.colors, &colors);
+        let materials = compact(&mut header2.materials, &materials);
+        let physical_materials = compact(&mut header2.physical_materials, &physical_materials);
+        let after = header2.brick_assets.len()
+            + header2.colors.len()
+            + header2.materials.len()
+            + header2.physical_materials.len();
+
+        let remap = |remap: &[u32], index: &mut u32| {
+            if let Some(&new) = remap.get(*index as usize) {
+                *index = new;
+            }
+        };
+        for brick in self.bricks.iter_mut() {
+            remap(&assets, &mut brick.asset_name_index);
+            remap(&materials, &mut brick.material_index);
+            remap(&physical_materials, &mut brick.physical_index);
+            if let BrickColor::Index(index) = &mut brick.color {
+                remap(&colors, index);
+            }
+        }
+
+        before - after
+    }
+
+    /// Recount the bricks owned by each of `Header2`'s `brick_owners` from the bricks'
+    /// `owner_index`es.
+    ///
+    /// Fails, changing nothing, if a brick refers to an owner that does not exist.
+    pub fn recompute_owners(&mut self) -> Result<(), ValidationError> {
+        let owners = &mut self.header2.brick_owners;
+        let mut counts = vec![0u32; owners.len()];
+
+        for (i, brick) in self.bricks.iter().enumerate() {
+            if let Some(owner) = owner_position(brick.owner_index) {
+                match counts.get_mut(owner) {
+                    Some(count) => *count += 1,
+                    None => {
+                        return Err(ValidationError::IndexOutOfRange {
+                            brick: i,
+                            field: "owner_index",
+                            index: brick.owner_index,
+                            len: owners.len(),
+                        })
+                    }
+                }
+            }
+        }
+
+        for (owner, count) in owners.iter_mut().zip(counts) {
+            owner.bricks = count;
+        }
+        Ok(())
+    }
+
+    /// Recount the bricks owned by each owner (see `recompute_owners`), then remove the
+    /// owners that own no bricks, updating the bricks' `owner_index`es to match.
+    pub fn prune_owners(&mut self) -> Result<(), ValidationError> {
+        self.recompute_owners()?;
+
+        // new 1-based index of each old owner, or 0 if it is removed
+        let mut remap = vec![0u32; self.header2.brick_owners.len()];
+        let mut next = 0;
+        for (i, owner) in self.header2.brick_owners.iter().enumerate() {
+            if owner.bricks > 0 {
+                next += 1;
+                remap[i] = next;
+            }
+        }
+
+        self.header2.brick_owners.retain(|owner| owner.bricks > 0);
+        for brick in self.bricks.iter_mut() {
+            if let Some(owner) = owner_position(brick.owner_index) {
+                brick.owner_index = remap[owner];
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove duplicate colors from `Header2`'s `colors`, keeping the first of each, and
+    /// point every `BrickColor::Index` at the remaining entry. Returns how many were removed.
+    ///
+    /// `BrickColor::Unique` bricks and indices out of range of the palette are left unchanged.
+    pub fn dedup_colors(&mut self) -> usize {
+        let mut first: HashMap<&Color, u32> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.header2.colors.len());
+        let mut keep = Vec::with_capacity(self.header2.colors.len());
+        for color in self.header2.colors.iter() {
+            let next = first.len() as u32;
+            let index = *first.entry(color).or_insert(next);
+            keep.push(index == next);
+            remap.push(index);
+        }
+
+        for brick in self.bricks.iter_mut() {
+            if let BrickColor::Index(index) = &mut brick.color {
+                if let Some(&new) = remap.get(*index as usize) {
+                    *index = new;
+                }
+            }
+        }
+
+        let before = self.header2.colors.len();
+        let mut keep = keep.into_iter();
+        self.header2.colors.retain(|_| keep.next().unwrap());
+        before - self.header2.colors.len()
+    }
+
+    /// Convert every `BrickColor::Unique` brick whose color is already in `Header2`'s
+    /// `colors` into a `BrickColor::Index`. Returns how many bricks were converted.
+    pub fn fold_unique_colors(&mut self) -> usize {
+        let mut palette: HashMap<&Color, u32> = HashMap::new();
+        for (i, color) in self.header2.colors.iter().enumerate() {
+            palette.entry(color).or_insert(i as u32);
+        }
+
+        let mut folded = 0;
+        for brick in self.bricks.iter_mut() {
+            if let BrickColor::Unique(color) = &brick.color {
+                // unique colors are always opaque
+                let opaque = Color {
+                    a: 255,
+                    ..color.clone()
+                };
+                if let Some(&index) = palette.get(&opaque) {
+                    brick.color = BrickColor::Index(index);
+                    folded += 1;
+                }
+            }
+        }
+        folded
+    }
+
+    /// Reserve capacity for at least `additional` more bricks.
+    pub fn reserve_bricks(&mut self, additional: usize) {
+        self.bricks.reserve(additional);
+    }
+
+    /// Get the brick groups in this save, mapping each group's name to the indices of its bricks.
+    ///
+    /// Brickadia has no native brick groups, so groups are stored as property-less components
+    /// named `GROUP_COMPONENT_PREFIX` followed by the group's name. Membership is read from the
+    /// bricks themselves, so indices stay correct as bricks are added or removed.
+    pub fn groups(&self) -> HashMap<&str, Vec<u32>> {
+        let mut groups: HashMap<&str, Vec<u32>> = HashMap::new();
+        for (i, brick) in self.bricks.iter().enumerate() {
+            for name in brick.components.keys() {
+                if let Some(group) = name.strip_prefix(GROUP_COMPONENT_PREFIX) {
+                    groups.entry(group).or_default().push(i as u32);
+                }
+            }
+        }
+        groups
+    }
+
+    /// Add the bricks at `brick_indices` to the group `name`, creating it if needed.
+    ///
+    /// Indices that are out of range are ignored.
+    pub fn add_group(&mut self, name: &str, brick_indices: &[u32]) {
+        let component_name = format!("{}{}", GROUP_COMPONENT_PREFIX, name);
+
+        for &i in brick_indices {
+            if let Some(brick) = self.bricks.get_mut(i as usize) {
+                brick
+                    .components
+                    .insert(component_name.clone(), HashMap::new());
+            }
+        }
+
+        let bricks = &self.bricks;
+        let component = self.components.entry(component_name.clone()).or_default();
+        component.brick_indices = (0..bricks.len() as u32)
+            .filter(|&i| bricks[i as usize].components.contains_key(&component_name))
+            .collect();
+    }
+
+    /// Remove the group `name` from every brick.
+    pub fn remove_group(&mut self, name: &str) {
+        let component_name = format!("{}{}", GROUP_COMPONENT_PREFIX, name);
+        for brick in self.bricks.iter_mut() {
+            brick.components.remove(&component_name);
+        }
+        self.components.remove(&component_name);
+    }
+
+    /// Set the visibility of every brick in the save.
+    pub fn set_all_visible(&mut self, visible: bool) {
+        for brick in self.bricks.iter_mut() {
+            brick.visibility = visible;
+        }
+    }
+
+    /// Set the visibility of every brick using the material named `material`.
+    ///
+    /// Does nothing if `material` is not in `Header2`'s `materials`.
+    pub fn toggle_visibility_by_material(&mut self, material: &str, visible: bool) {
+        let index = match self.header2.materials.iter().position(|m| m == material) {
+            Some(index) => index as u32,
+            None => return,
+        };
+
+        for brick in self.bricks.iter_mut() {
+            if brick.material_index == index {
+                brick.visibility = visible;
+            }
+        }
+    }
+
+    /// Clamp every brick's `material_intensity` to the range the game uses, 0 through
+    /// `MAX_MATERIAL_INTENSITY`.
+    pub fn normalize_intensities(&mut self) -> IntensityReport {
+        let mut report = IntensityReport::default();
+        for brick in self.bricks.iter_mut() {
+            if brick.material_intensity > MAX_MATERIAL_INTENSITY {
+                report.out_of_range += 1;
+                report.highest = report.highest.max(brick.material_intensity);
+                brick.material_intensity = MAX_MATERIAL_INTENSITY;
+            }
+        }
+        report
+    }
 }
 
 impl Default for SaveData {
     fn default() -> Self {
         SaveData {
-            version: SAVE_VERSION,
+            save_version: SAVE_VERSION,
             game_version: 0,
             header1: Header1::default(),
             header2: Header2::default(),
@@ -71,7 +807,18 @@ impl Default for SaveData {
     }
 }
 
-#[derive(Debug)]
+/// The result of `SaveData::normalize_intensities`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct IntensityReport {
+    /// How many bricks had an intensity above `MAX_MATERIAL_INTENSITY`.
+    pub out_of_range: usize,
+
+    /// The highest out of range intensity found, or 0 if there were none.
+    pub highest: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(default))]
 pub struct Header1 {
     /// The map the save was saved on.
@@ -86,12 +833,28 @@ pub struct Header1 {
     /// The host of the server in which the save was saved. Only available in save versions 8+.
     pub host: Option<User>,
 
-    /// The save time of the save.
-    #[cfg_attr(feature = "serialize", serde(skip))]
+    /// The save time of the save, as little-endian Unreal `FDateTime` ticks (100 nanosecond
+    /// intervals since 0001-01-01, not a Windows `FILETIME`). Serialized as an RFC 3339 UTC
+    /// timestamp. See `set_save_time` and `saved_at` to convert it.
+    #[cfg_attr(feature = "serialize", serde(with = "save_time_serde"))]
     pub save_time: [u8; 8],
 
     /// The number of bricks in the save.
     pub brick_count: u32,
+
+    /// Bytes found after the known fields of this header when it was read, e.g. fields added
+    /// by a newer game version. They are written back as-is after the known fields, so
+    /// editing a save doesn't strip them. As their meaning is unknown, changing the save
+    /// (e.g. its brick count) may leave them inconsistent with the rest of it.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(
+            rename = "header1_raw_extra",
+            default,
+            skip_serializing_if = "Vec::is_empty"
+        )
+    )]
+    pub raw_extra: Vec<u8>,
 }
 
 impl Default for Header1 {
@@ -103,11 +866,180 @@ impl Default for Header1 {
             host: None,
             save_time: [0u8; 8],
             brick_count: 0,
+            raw_extra: vec![],
+        }
+    }
+}
+
+/// `FDateTime` ticks (100 nanosecond intervals) per second.
+const TICKS_PER_SECOND: i64 = 10_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Days from 0001-01-01 to 1970-01-01.
+const UNIX_EPOCH_DAYS: i64 = 719_162;
+
+/// `FDateTime` ticks from 0001-01-01 to 1970-01-01.
+const UNIX_EPOCH_TICKS: i64 = UNIX_EPOCH_DAYS * SECONDS_PER_DAY * TICKS_PER_SECOND;
+
+impl Header1 {
+    /// Set `save_time` to `time`, truncated to whole ticks (100 nanoseconds).
+    ///
+    /// Times before 0001-01-01 are clamped to it.
+    pub fn set_save_time(&mut self, time: SystemTime) {
+        let nanos = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_nanos() as i128,
+            Err(e) => -(e.duration().as_nanos() as i128),
+        };
+        self.save_time = ticks_from_unix_nanos(nanos);
+    }
+
+    /// Get `save_time` as a `SystemTime`, or `None` if the platform can't represent it.
+    pub fn saved_at(&self) -> Option<SystemTime> {
+        let ticks = i64::from_le_bytes(self.save_time) as i128 - UNIX_EPOCH_TICKS as i128;
+        let (seconds, fraction) = (
+            ticks.unsigned_abs() / TICKS_PER_SECOND as u128,
+            ticks.unsigned_abs() % TICKS_PER_SECOND as u128,
+        );
+        let duration = Duration::new(u64::try_from(seconds).ok()?, fraction as u32 * 100);
+        if ticks >= 0 {
+            UNIX_EPOCH.checked_add(duration)
+        } else {
+            UNIX_EPOCH.checked_sub(duration)
+        }
+    }
+
+    /// Set `save_time` to `time`, like `set_save_time`.
+    #[cfg(feature = "chrono")]
+    pub fn set_save_datetime(&mut self, time: chrono::DateTime<chrono::Utc>) {
+        let nanos =
+            time.timestamp() as i128 * 1_000_000_000 + time.timestamp_subsec_nanos() as i128;
+        self.save_time = ticks_from_unix_nanos(nanos);
+    }
+
+    /// Get `save_time` as a UTC `DateTime`, or `None` if chrono can't represent it.
+    #[cfg(feature = "chrono")]
+    pub fn saved_at_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let ticks = i64::from_le_bytes(self.save_time) as i128 - UNIX_EPOCH_TICKS as i128;
+        let seconds = ticks.div_euclid(TICKS_PER_SECOND as i128);
+        let fraction = ticks.rem_euclid(TICKS_PER_SECOND as i128);
+        chrono::DateTime::from_timestamp(i64::try_from(seconds).ok()?, fraction as u32 * 100)
+    }
+}
+
+/// Convert a time in nanoseconds since 1970-01-01 to `save_time` bytes, clamping it to what
+/// the field can store.
+fn ticks_from_unix_nanos(nanos: i128) -> [u8; 8] {
+    let ticks = UNIX_EPOCH_TICKS as i128 + nanos.div_euclid(100);
+    (ticks.clamp(0, i64::MAX as i128) as i64).to_le_bytes()
+}
+
+/// (De)serializes `Header1::save_time` as an RFC 3339 timestamp, e.g. `2021-06-24T18:42:49.1350000Z`.
+#[cfg(feature = "serialize")]
+mod save_time_serde {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use super::{SECONDS_PER_DAY, TICKS_PER_SECOND, UNIX_EPOCH_DAYS};
+
+    /// The latest year a save time can be parsed in.
+    const MAX_YEAR: i64 = 99_999;
+
+    // civil date <-> days since 1970-01-01, after Howard Hinnant's algorithms
+    fn civil_from_days(days: i64) -> (i64, i64, i64) {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let d = doy - (153 * mp + 2) / 5 + 1;
+        let m = if mp < 10 { mp + 3 } else { mp - 9 };
+        let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+        (y, m, d)
+    }
+
+    fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+        let y = if m <= 2 { y - 1 } else { y };
+        let era = y.div_euclid(400);
+        let yoe = y.rem_euclid(400);
+        let mp = if m > 2 { m - 3 } else { m + 9 };
+        let doy = (153 * mp + 2) / 5 + d - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    pub fn serialize<S: Serializer>(save_time: &[u8; 8], serializer: S) -> Result<S::Ok, S::Error> {
+        let ticks = i64::from_le_bytes(*save_time);
+        let seconds = ticks.div_euclid(TICKS_PER_SECOND);
+        let fraction = ticks.rem_euclid(TICKS_PER_SECOND);
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+        let (y, m, d) = civil_from_days(days - UNIX_EPOCH_DAYS);
+
+        serializer.serialize_str(&format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:07}Z",
+            y,
+            m,
+            d,
+            time / 3600,
+            time / 60 % 60,
+            time % 60,
+            fraction
+        ))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 8], D::Error> {
+        let string = String::deserialize(deserializer)?;
+        let invalid = || de::Error::custom(format!("invalid save time {:?}", string));
+
+        // YYYY-MM-DDTHH:MM:SS[.fffffff]Z
+        let rest = string.strip_suffix('Z').ok_or_else(invalid)?;
+        let (date, time) = rest.split_once('T').ok_or_else(invalid)?;
+        let (time, fraction) = time.split_once('.').unwrap_or((time, "0"));
+
+        let parse =
+            |part: Option<&str>| part.and_then(|p| p.parse::<i64>().ok()).ok_or_else(invalid);
+        let mut date = date.split('-');
+        let (y, m, d) = (
+            parse(date.next())?,
+            parse(date.next())?,
+            parse(date.next())?,
+        );
+        let mut time = time.split(':');
+        let (hh, mm, ss) = (
+            parse(time.next())?,
+            parse(time.next())?,
+            parse(time.next())?,
+        );
+        if date.next().is_some() || time.next().is_some() || fraction.len() > 7 {
+            return Err(invalid());
+        }
+        let fraction = parse(Some(fraction))? * 10i64.pow(7 - fraction.len() as u32);
+
+        // the year is bounded so days_from_civil can't overflow, and times past what the
+        // ticks can hold are caught below; a day past the end of its month doesn't round trip
+        let in_range = (0..=MAX_YEAR).contains(&y)
+            && (1..=12).contains(&m)
+            && (1..=31).contains(&d)
+            && civil_from_days(days_from_civil(y, m, d)) == (y, m, d)
+            && (0..24).contains(&hh)
+            && (0..60).contains(&mm)
+            && (0..60).contains(&ss)
+            && fraction >= 0;
+        if !in_range {
+            return Err(invalid());
         }
+
+        let days = days_from_civil(y, m, d) + UNIX_EPOCH_DAYS;
+        days.checked_mul(SECONDS_PER_DAY)
+            .and_then(|s| s.checked_add(hh * 3600 + mm * 60 + ss))
+            .and_then(|s| s.checked_mul(TICKS_PER_SECOND))
+            .and_then(|t| t.checked_add(fraction))
+            .map(i64::to_le_bytes)
+            .ok_or_else(invalid)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(default))]
 pub struct Header2 {
     /// A list of mods, each a String.
@@ -127,6 +1059,327 @@ pub struct Header2 {
 
     /// A list of physical materials. Empty if save version is
     pub physical_materials: Vec<String>,
+
+    /// Bytes found after the known fields of this header when it was read. See
+    /// `Header1::raw_extra`.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(
+            rename = "header2_raw_extra",
+            default,
+            skip_serializing_if = "Vec::is_empty"
+        )
+    )]
+    pub raw_extra: Vec<u8>,
+}
+
+/// A brick material, as named in `Header2::materials`.
+///
+/// Built-in materials have their own variants, and any other name (e.g. from a mod) is kept as
+/// `Material::Custom`. Converting to and from strings is lossless.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Material {
+    /// `BMC_Plastic`
+    Plastic,
+    /// `BMC_Glow`
+    Glow,
+    /// `BMC_Glass`
+    Glass,
+    /// `BMC_Metallic`
+    Metallic,
+    /// `BMC_Hologram`
+    Hologram,
+    /// `BMC_Ghost`
+    Ghost,
+    /// `BMC_Ghost_Fail`
+    GhostFail,
+    /// `BMC_Hidden`
+    Hidden,
+    /// Any other material, by name.
+    Custom(String),
+}
+
+impl Material {
+    /// Get the name of this material as it appears in a save.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Material::Plastic => "BMC_Plastic",
+            Material::Glow => "BMC_Glow",
+            Material::Glass => "BMC_Glass",
+            Material::Metallic => "BMC_Metallic",
+            Material::Hologram => "BMC_Hologram",
+            Material::Ghost => "BMC_Ghost",
+            Material::GhostFail => "BMC_Ghost_Fail",
+            Material::Hidden => "BMC_Hidden",
+            Material::Custom(name) => name,
+        }
+    }
+}
+
+impl From<&str> for Material {
+    fn from(name: &str) -> Self {
+        match name {
+            "BMC_Plastic" => Material::Plastic,
+            "BMC_Glow" => Material::Glow,
+            "BMC_Glass" => Material::Glass,
+            "BMC_Metallic" => Material::Metallic,
+            "BMC_Hologram" => Material::Hologram,
+            "BMC_Ghost" => Material::Ghost,
+            "BMC_Ghost_Fail" => Material::GhostFail,
+            "BMC_Hidden" => Material::Hidden,
+            _ => Material::Custom(name.to_owned()),
+        }
+    }
+}
+
+impl FromStr for Material {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Material::from(s))
+    }
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<Material> for String {
+    fn from(material: Material) -> Self {
+        match material {
+            Material::Custom(name) => name,
+            material => material.as_str().to_owned(),
+        }
+    }
+}
+
+/// A brick asset, as named in `Header2::brick_assets`.
+///
+/// The built-in procedural assets have their own variants. Any other name, including static
+/// mesh assets like `B_1x1_Round` and modded assets, is kept as `BrickAsset::Custom`.
+/// Converting to and from strings is lossless.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BrickAsset {
+    /// `PB_DefaultBrick`
+    Brick,
+    /// `PB_DefaultMicroBrick`
+    MicroBrick,
+    /// `PB_DefaultMicroWedge`
+    MicroWedge,
+    /// `PB_DefaultPole`
+    Pole,
+    /// `PB_DefaultRamp`
+    Ramp,
+    /// `PB_DefaultRampCorner`
+    RampCorner,
+    /// `PB_DefaultRampCrest`
+    RampCrest,
+    /// `PB_DefaultRampInnerCorner`
+    RampInnerCorner,
+    /// `PB_DefaultSideWedge`
+    SideWedge,
+    /// `PB_DefaultSideWedgeTile`
+    SideWedgeTile,
+    /// `PB_DefaultSmoothTile`
+    SmoothTile,
+    /// `PB_DefaultTile`
+    Tile,
+    /// `PB_DefaultWedge`
+    Wedge,
+    /// Any other asset, by name.
+    Custom(String),
+}
+
+impl BrickAsset {
+    /// Get the name of this asset as it appears in a save.
+    pub fn as_str(&self) -> &str {
+        match self {
+            BrickAsset::Brick => "PB_DefaultBrick",
+            BrickAsset::MicroBrick => "PB_DefaultMicroBrick",
+            BrickAsset::MicroWedge => "PB_DefaultMicroWedge",
+            BrickAsset::Pole => "PB_DefaultPole",
+            BrickAsset::Ramp => "PB_DefaultRamp",
+            BrickAsset::RampCorner => "PB_DefaultRampCorner",
+            BrickAsset::RampCrest => "PB_DefaultRampCrest",
+            BrickAsset::RampInnerCorner => "PB_DefaultRampInnerCorner",
+            BrickAsset::SideWedge => "PB_DefaultSideWedge",
+            BrickAsset::SideWedgeTile => "PB_DefaultSideWedgeTile",
+            BrickAsset::SmoothTile => "PB_DefaultSmoothTile",
+            BrickAsset::Tile => "PB_DefaultTile",
+            BrickAsset::Wedge => "PB_DefaultWedge",
+            BrickAsset::Custom(name) => name,
+        }
+    }
+}
+
+impl From<&str> for BrickAsset {
+    fn from(name: &str) -> Self {
+        match name {
+            "PB_DefaultBrick" => BrickAsset::Brick,
+            "PB_DefaultMicroBrick" => BrickAsset::MicroBrick,
+            "PB_DefaultMicroWedge" => BrickAsset::MicroWedge,
+            "PB_DefaultPole" => BrickAsset::Pole,
+            "PB_DefaultRamp" => BrickAsset::Ramp,
+            "PB_DefaultRampCorner" => BrickAsset::RampCorner,
+            "PB_DefaultRampCrest" => BrickAsset::RampCrest,
+            "PB_DefaultRampInnerCorner" => BrickAsset::RampInnerCorner,
+            "PB_DefaultSideWedge" => BrickAsset::SideWedge,
+            "PB_DefaultSideWedgeTile" => BrickAsset::SideWedgeTile,
+            "PB_DefaultSmoothTile" => BrickAsset::SmoothTile,
+            "PB_DefaultTile" => BrickAsset::Tile,
+            "PB_DefaultWedge" => BrickAsset::Wedge,
+            _ => BrickAsset::Custom(name.to_owned()),
+        }
+    }
+}
+
+impl FromStr for BrickAsset {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(BrickAsset::from(s))
+    }
+}
+
+impl fmt::Display for BrickAsset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<BrickAsset> for String {
+    fn from(asset: BrickAsset) -> Self {
+        match asset {
+            BrickAsset::Custom(name) => name,
+            asset => asset.as_str().to_owned(),
+        }
+    }
+}
+
+/// Brickadia's default color palette, in RGB, in the order the game lists it.
+#[rustfmt::skip]
+pub static DEFAULT_PALETTE: [[u8; 3]; 96] = [
+    [255, 255, 255], [184, 184, 184], [136, 136, 136], [114, 114, 114], [90, 90, 90], [57, 57, 57],
+    [35, 35, 35], [24, 24, 24], [17, 17, 17], [6, 6, 6], [2, 2, 2], [0, 0, 0],
+    [87, 5, 9], [235, 6, 6], [255, 29, 3], [246, 73, 6], [235, 157, 6], [61, 164, 4],
+    [9, 139, 5], [3, 16, 255], [12, 244, 255], [163, 35, 85], [48, 8, 72], [14, 6, 49],
+    [41, 25, 25], [96, 71, 73], [181, 131, 134], [45, 44, 27], [114, 109, 65], [144, 139, 100],
+    [27, 45, 28], [65, 114, 68], [100, 144, 103], [30, 39, 41], [71, 92, 96], [131, 171, 181],
+    [23, 5, 2], [90, 16, 5], [77, 20, 1], [77, 30, 7], [144, 60, 18], [166, 104, 62],
+    [255, 159, 78], [255, 121, 78], [50, 20, 13], [21, 12, 3], [51, 33, 13], [194, 163, 58],
+    [19, 2, 1], [73, 4, 1], [190, 23, 18], [190, 59, 53], [255, 149, 156], [255, 79, 38],
+    [255, 41, 2], [171, 54, 27], [109, 64, 5], [171, 99, 8], [255, 146, 11], [255, 175, 47],
+    [22, 37, 1], [67, 80, 12], [122, 144, 30], [101, 255, 81], [13, 204, 47], [0, 77, 0],
+    [11, 54, 11], [5, 30, 3], [5, 18, 5], [8, 43, 27], [9, 96, 53], [8, 146, 66],
+    [5, 13, 17], [11, 30, 44], [1, 34, 64], [0, 65, 122], [8, 118, 200], [5, 152, 171],
+    [80, 147, 163], [134, 250, 255], [86, 119, 242], [37, 55, 235], [12, 25, 156], [1, 4, 44],
+    [8, 0, 30], [18, 0, 57], [56, 19, 100], [141, 45, 255], [255, 93, 255], [253, 149, 255],
+    [255, 58, 116], [91, 18, 55], [255, 24, 255], [255, 0, 55], [127, 0, 29], [55, 0, 55],
+];
+
+impl Header2 {
+    /// Replace `materials` with the game's standard materials, the ones saves before version
+    /// 2 use implicitly, ordered so that material index 0 is plastic.
+    pub fn with_default_materials(mut self) -> Self {
+        self.materials = vec![
+            "BMC_Plastic".into(),
+            "BMC_Glow".into(),
+            "BMC_Glass".into(),
+            "BMC_Metallic".into(),
+            "BMC_Hologram".into(),
+        ];
+        self
+    }
+
+    /// Replace `colors` with the game's default palette (see `DEFAULT_PALETTE`).
+    pub fn with_default_palette(mut self) -> Self {
+        self.colors = DEFAULT_PALETTE
+            .iter()
+            .map(|&rgb| Color::from_bytes_rgb(rgb))
+            .collect();
+        self
+    }
+
+    /// Get the owner a brick's `owner_index` refers to, or `None` if the brick is public or
+    /// its owner index is out of range.
+    pub fn owner(&self, owner_index: u32) -> Option<&BrickOwner> {
+        owner_position(owner_index).and_then(|i| self.brick_owners.get(i))
+    }
+
+    /// Like `owner`, but mutable, e.g. to update the owner's brick count.
+    pub fn owner_mut(&mut self, owner_index: u32) -> Option<&mut BrickOwner> {
+        owner_position(owner_index).and_then(move |i| self.brick_owners.get_mut(i))
+    }
+
+    /// Get the material at `index` in `materials`.
+    pub fn material(&self, index: u32) -> Option<Material> {
+        self.materials
+            .get(index as usize)
+            .map(|name| Material::from(name.as_str()))
+    }
+
+    /// Get the index of `material` in `materials`.
+    pub fn material_index(&self, material: &Material) -> Option<u32> {
+        index_of(&self.materials, material.as_str())
+    }
+
+    /// Get the index of `material` in `materials`, adding it if it is not there.
+    pub fn add_material(&mut self, material: Material) -> u32 {
+        self.material_index(&material)
+            .unwrap_or_else(|| push_index(&mut self.materials, material.into()))
+    }
+
+    /// Get the brick asset at `index` in `brick_assets`.
+    pub fn brick_asset(&self, index: u32) -> Option<BrickAsset> {
+        self.brick_assets
+            .get(index as usize)
+            .map(|name| BrickAsset::from(name.as_str()))
+    }
+
+    /// Get the index of `asset` in `brick_assets`.
+    pub fn brick_asset_index(&self, asset: &BrickAsset) -> Option<u32> {
+        index_of(&self.brick_assets, asset.as_str())
+    }
+
+    /// Get the index of `asset` in `brick_assets`, adding it if it is not there.
+    pub fn add_brick_asset(&mut self, asset: BrickAsset) -> u32 {
+        self.brick_asset_index(&asset)
+            .unwrap_or_else(|| push_index(&mut self.brick_assets, asset.into()))
+    }
+}
+
+/// Remove the entries of `table` that aren't `used`, returning the new index of each old
+/// entry. The indices returned for removed entries are meaningless.
+fn compact<T>(table: &mut Vec<T>, used: &[bool]) -> Vec<u32> {
+    let mut remap = Vec::with_capacity(used.len());
+    let mut next = 0;
+    for &used in used {
+        remap.push(next);
+        if used {
+            next += 1;
+        }
+    }
+
+    let mut used = used.iter();
+    table.retain(|_| *used.next().unwrap());
+    remap
+}
+
+/// The position in `Header2::brick_owners` of the owner a brick's `owner_index` refers to, or
+/// `None` if the brick is public. Owner indices are 1-indexed, with 0 being public.
+pub(crate) fn owner_position(owner_index: u32) -> Option<usize> {
+    (owner_index as usize).checked_sub(1)
+}
+
+fn index_of(table: &[String], name: &str) -> Option<u32> {
+    table.iter().position(|n| n == name).map(|i| i as u32)
+}
+
+fn push_index(table: &mut Vec<String>, name: String) -> u32 {
+    table.push(name);
+    table.len() as u32 - 1
 }
 
 impl Default for Header2 {
@@ -138,11 +1391,16 @@ impl Default for Header2 {
             materials: vec!["BMC_Plastic".into()],
             brick_owners: vec![],
             physical_materials: vec!["BPMC_Default".into()],
+            raw_extra: vec![],
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// The preview image of a save.
+///
+/// A save's preview is not serialized as part of its `SaveData`, to keep the output small.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Preview {
     None,
     PNG(Vec<u8>),
@@ -153,9 +1411,14 @@ pub enum Preview {
 impl Preview {
     pub fn from_reader(r: &mut impl Read) -> Result<Self, ReadError> {
         fn read_bytes(r: &mut impl Read) -> Result<Vec<u8>, ReadError> {
-            let len = r.read_i32::<LittleEndian>()?;
-            let mut vec = vec![0u8; len as usize];
-            r.read_exact(&mut vec)?;
+            let len = crate::ext::read::check_len(r.read_i32::<LittleEndian>()?)?;
+
+            // the buffer grows with the data actually read, as the length can't be trusted
+            let mut vec = vec![];
+            r.take(len as u64).read_to_end(&mut vec)?;
+            if vec.len() != len {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
             Ok(vec)
         }
 
@@ -177,7 +1440,7 @@ impl Preview {
         }
     }
 
-    pub fn into_bytes(self) -> Option<Vec<u8>> {
+    pub fn as_bytes(&self) -> Option<&[u8]> {
         match self {
             Preview::None => None,
             Preview::PNG(bytes) => Some(bytes),
@@ -186,13 +1449,19 @@ impl Preview {
         }
     }
 
-    pub fn is_none(&self) -> bool {
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
         match self {
-            Preview::None => true,
-            _ => false,
+            Preview::None => None,
+            Preview::PNG(bytes) => Some(bytes),
+            Preview::JPEG(bytes) => Some(bytes),
+            Preview::Unknown(_, bytes) => Some(bytes),
         }
     }
 
+    pub fn is_none(&self) -> bool {
+        matches!(self, Preview::None)
+    }
+
     pub fn is_some(&self) -> bool {
         !self.is_none()
     }
@@ -200,21 +1469,208 @@ impl Preview {
     pub fn unwrap(self) -> Vec<u8> {
         self.into_bytes().unwrap()
     }
+
+    /// Read a PNG preview from the file at `path`.
+    ///
+    /// Fails with `PreviewError::FormatMismatch` if the file isn't a PNG.
+    pub fn from_png_file(path: impl AsRef<Path>) -> Result<Self, PreviewError> {
+        Self::from_png_reader(File::open(path)?)
+    }
+
+    /// Read a JPEG preview from the file at `path`.
+    ///
+    /// Fails with `PreviewError::FormatMismatch` if the file isn't a JPEG.
+    pub fn from_jpeg_file(path: impl AsRef<Path>) -> Result<Self, PreviewError> {
+        Self::from_jpeg_reader(File::open(path)?)
+    }
+
+    /// Read a PNG preview from all of `reader`'s bytes, see `from_png_file`.
+    pub fn from_png_reader(mut reader: impl Read) -> Result<Self, PreviewError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        check_magic(&bytes, PNG_MAGIC, "PNG")?;
+        Ok(Preview::PNG(bytes))
+    }
+
+    /// Read a JPEG preview from all of `reader`'s bytes, see `from_jpeg_file`.
+    pub fn from_jpeg_reader(mut reader: impl Read) -> Result<Self, PreviewError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        check_magic(&bytes, JPEG_MAGIC, "JPEG")?;
+        Ok(Preview::JPEG(bytes))
+    }
+
+    /// Decode the preview into an image.
+    #[cfg(feature = "image")]
+    pub fn decode(&self) -> Result<DynamicImage, PreviewError> {
+        let (bytes, format) = match self {
+            Preview::None => return Err(PreviewError::Missing),
+            Preview::PNG(bytes) => (bytes, ImageFormat::Png),
+            Preview::JPEG(bytes) => (bytes, ImageFormat::Jpeg),
+            Preview::Unknown(byte, _) => return Err(PreviewError::UnknownType(*byte)),
+        };
+        Ok(image::load_from_memory_with_format(bytes, format)?)
+    }
+
+    /// Encode `image` as a preview in `format`, which must be `ImageFormat::Png` or
+    /// `ImageFormat::Jpeg`.
+    #[cfg(feature = "image")]
+    pub fn from_image(image: &DynamicImage, format: ImageFormat) -> Result<Self, PreviewError> {
+        let mut bytes = Cursor::new(vec![]);
+        match format {
+            ImageFormat::Png => {
+                image.write_to(&mut bytes, format)?;
+                Ok(Preview::PNG(bytes.into_inner()))
+            }
+            ImageFormat::Jpeg => {
+                // JPEG has no alpha channel
+                DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut bytes, format)?;
+                Ok(Preview::JPEG(bytes.into_inner()))
+            }
+            other => Err(PreviewError::UnsupportedFormat(other)),
+        }
+    }
 }
 
+/// An error reading, decoding or encoding a `Preview`.
+#[derive(Error, Debug)]
+pub enum PreviewError {
+    #[error("the save has no preview")]
+    Missing,
+    #[error("unknown preview type {0}")]
+    UnknownType(u8),
+    #[error("preview data is not a {expected} image")]
+    FormatMismatch { expected: &'static str },
+    #[error("generic io error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[cfg(feature = "image")]
+    #[error("previews can only be PNG or JPEG, not {0:?}")]
+    UnsupportedFormat(ImageFormat),
+    #[cfg(feature = "image")]
+    #[error("image error: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+const PNG_MAGIC: &[u8] = &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const JPEG_MAGIC: &[u8] = &[0xff, 0xd8, 0xff];
+
+/// Check that `bytes` start with the `magic` bytes of the `expected` format.
+fn check_magic(bytes: &[u8], magic: &[u8], expected: &'static str) -> Result<(), PreviewError> {
+    if bytes.starts_with(magic) {
+        Ok(())
+    } else {
+        Err(PreviewError::FormatMismatch { expected })
+    }
+}
+
+/// The value of a component property on a brick.
+///
+/// These are the property types the save format can encode. Each component declares the
+/// type name (see `type_name`) of each of its properties in `Component::properties`.
+///
+/// When serialized, values are tagged with their type (e.g. `{"Float": 1.0}`), as several
+/// types share the same untagged representation.
 #[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(untagged))]
-pub enum UnrealType {
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum UnrealValue {
+    /// A class or object reference, by path. Declared as `Class` or `Object`.
     Class(String),
     String(String),
     Boolean(bool),
     Float(f32),
     Color(Color),
     Byte(u8),
+    /// A rotator, as pitch, yaw and roll in degrees.
     Rotator(f32, f32, f32),
 }
 
-#[derive(Debug, Clone)]
+/// The old name of `UnrealValue`.
+pub type UnrealType = UnrealValue;
+
+impl UnrealValue {
+    /// The default value of a property declared with `type_name` in `Component::properties`:
+    /// empty strings and classes, false, zero, a black color or a zero rotator. Returns `None`
+    /// if the type is not known.
+    pub fn default_for(type_name: &str) -> Option<UnrealValue> {
+        Some(match type_name {
+            "Class" | "Object" => UnrealValue::Class(String::new()),
+            "String" => UnrealValue::String(String::new()),
+            "Boolean" => UnrealValue::Boolean(false),
+            "Float" => UnrealValue::Float(0.0),
+            "Color" => UnrealValue::Color(Color::from_rgba(0, 0, 0, 255)),
+            "Byte" => UnrealValue::Byte(0),
+            "Rotator" => UnrealValue::Rotator(0.0, 0.0, 0.0),
+            _ => return None,
+        })
+    }
+
+    /// The type name this value is declared with in `Component::properties`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            UnrealValue::Class(_) => "Class",
+            UnrealValue::String(_) => "String",
+            UnrealValue::Boolean(_) => "Boolean",
+            UnrealValue::Float(_) => "Float",
+            UnrealValue::Color(_) => "Color",
+            UnrealValue::Byte(_) => "Byte",
+            UnrealValue::Rotator(_, _, _) => "Rotator",
+        }
+    }
+}
+
+impl From<String> for UnrealValue {
+    fn from(value: String) -> Self {
+        UnrealValue::String(value)
+    }
+}
+
+impl From<&str> for UnrealValue {
+    fn from(value: &str) -> Self {
+        UnrealValue::String(value.into())
+    }
+}
+
+impl From<bool> for UnrealValue {
+    fn from(value: bool) -> Self {
+        UnrealValue::Boolean(value)
+    }
+}
+
+impl From<f32> for UnrealValue {
+    fn from(value: f32) -> Self {
+        UnrealValue::Float(value)
+    }
+}
+
+impl From<u8> for UnrealValue {
+    fn from(value: u8) -> Self {
+        UnrealValue::Byte(value)
+    }
+}
+
+impl From<Color> for UnrealValue {
+    fn from(value: Color) -> Self {
+        UnrealValue::Color(value)
+    }
+}
+
+/// Formats the value as Unreal's text export would.
+impl fmt::Display for UnrealValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnrealValue::Class(s) | UnrealValue::String(s) => write!(f, "{}", s),
+            UnrealValue::Boolean(b) => write!(f, "{}", if *b { "True" } else { "False" }),
+            UnrealValue::Float(x) => write!(f, "{:.6}", x),
+            UnrealValue::Color(c) => write!(f, "(B={},G={},R={},A={})", c.b, c.g, c.r, c.a),
+            UnrealValue::Byte(b) => write!(f, "{}", b),
+            UnrealValue::Rotator(pitch, yaw, roll) => {
+                write!(f, "(Pitch={:.6},Yaw={:.6},Roll={:.6})", pitch, yaw, roll)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(default))]
 pub struct User {
     /// The user's name.
@@ -234,7 +1690,7 @@ impl Default for User {
 }
 
 /// A brick owner. Similar to a user, but stores an u32 representing bricks in save.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct BrickOwner {
     /// The brick owner's name.
@@ -264,6 +1720,13 @@ impl BrickOwner {
 }
 
 /// A color, in RGBA.
+///
+/// Saves store colors in two channel orders:
+///
+/// * Palette colors (`Header2::colors`), `Color` component properties, and unique brick colors
+///   before save version 9 are stored as BGRA, see `from_bgra` and `to_bgra_bytes`.
+/// * Unique brick colors (`BrickColor::Unique`) are stored as RGB with no alpha, which is
+///   read back as 255. See `to_rgb_bytes`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Color {
     pub r: u8,
@@ -322,6 +1785,26 @@ impl<'de> Deserialize<'de> for Color {
 }
 
 impl Color {
+    /// Create a color from its channels, in RGBA order.
+    pub fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Create a color from its channels, in BGRA order, as palette colors are stored.
+    pub fn from_bgra(b: u8, g: u8, r: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Get the color's bytes in RGB order, as unique brick colors are stored. Alpha is dropped.
+    pub fn to_rgb_bytes(&self) -> [u8; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    /// Get the color's bytes in BGRA order, as palette colors are stored.
+    pub fn to_bgra_bytes(&self) -> [u8; 4] {
+        [self.b, self.g, self.r, self.a]
+    }
+
     /// Converts a slice of 4 bytes (bgra) to a Color (rgba).
     pub fn from_bytes_bgra(slice: [u8; 4]) -> Self {
         Color {
@@ -344,7 +1827,7 @@ impl Color {
 }
 
 /// A brick.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(default))]
 pub struct Brick {
     /// The asset name index of the brick, referring to `Header2`'s `brick_assets`.
@@ -385,7 +1868,7 @@ pub struct Brick {
     pub owner_index: u32,
 
     /// The components on this brick.
-    pub components: HashMap<String, HashMap<String, UnrealType>>,
+    pub components: HashMap<String, HashMap<String, UnrealValue>>,
 }
 
 #[cfg(feature = "serialize")]
@@ -422,6 +1905,259 @@ impl Default for Brick {
     }
 }
 
+impl Brick {
+    /// Get this brick's orientation.
+    pub fn orientation(&self) -> Orientation {
+        Orientation::new(self.direction, self.rotation)
+    }
+
+    /// Set this brick's direction and rotation from an orientation.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.direction = orientation.direction;
+        self.rotation = orientation.rotation;
+    }
+
+    /// Get the grid cells this brick occupies, each named by its minimum corner.
+    ///
+    /// The brick's size is resolved with `Size::resolve_with`, looking up its asset in
+    /// `assets` (the save's `header2.brick_assets`) and `sizes` (e.g. `util::BRICK_SIZE_MAP`).
+    /// The size's half extents are rotated into world axes by the brick's orientation, as in
+    /// `SaveData::bounds`, and every cell in the box from `position - extents` up to
+    /// `position + extents` is included. A brick whose size resolves to zero occupies no cells,
+    /// and neither does one too large for its cells to fit in `i32` coordinates.
+    #[cfg(feature = "util")]
+    pub fn occupied_cells(
+        &self,
+        assets: &[String],
+        sizes: &HashMap<&str, (u32, u32, u32)>,
+    ) -> Vec<(i32, i32, i32)> {
+        let asset_name = assets
+            .get(self.asset_name_index as usize)
+            .map_or("", |a| a.as_str());
+        let size = self.size.resolve_with(asset_name, sizes);
+        let extent = |axis| match crate::util::get_scale_axis(self, axis) {
+            0 => size.0,
+            1 => size.1,
+            _ => size.2,
+        };
+
+        // bricks too large to name their cells in i32 coordinates (or count them) have none
+        let range = |axis, center: i32| -> Option<(std::ops::Range<i32>, usize)> {
+            let extent = i32::try_from(extent(axis)).ok()?;
+            let range = center.checked_sub(extent)?..center.checked_add(extent)?;
+            Some((range, 2 * extent as usize))
+        };
+        let (x, y, z) = self.position;
+        let ((rx, lx), (ry, ly), (rz, lz)) = match (range(0, x), range(1, y), range(2, z)) {
+            (Some(rx), Some(ry), Some(rz)) => (rx, ry, rz),
+            _ => return vec![],
+        };
+        let count = match lx.checked_mul(ly).and_then(|n| n.checked_mul(lz)) {
+            Some(count) => count,
+            None => return vec![],
+        };
+
+        let mut cells = Vec::with_capacity(count);
+        for cx in rx {
+            for cy in ry.clone() {
+                for cz in rz.clone() {
+                    cells.push((cx, cy, cz));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Get the component `name` on this brick, if it has one.
+    pub fn component(&self, name: &str) -> Option<ComponentView<'_>> {
+        self.components
+            .get(name)
+            .map(|properties| ComponentView { properties })
+    }
+
+    /// Whether this brick has the component `name`.
+    pub fn has_component(&self, name: &str) -> bool {
+        self.components.contains_key(name)
+    }
+
+    /// Iterate over the names of the components on this brick, in no particular order.
+    pub fn component_names(&self) -> impl Iterator<Item = &str> {
+        self.components.keys().map(String::as_str)
+    }
+
+    /// Set the property `key` of the component `component` on this brick, adding the component
+    /// if the brick doesn't have it yet. Returns the property's previous value.
+    ///
+    /// The component and the property's type must also be described in `SaveData::components`
+    /// for the save to be written.
+    pub fn set_component_property(
+        &mut self,
+        component: &str,
+        key: &str,
+        value: impl Into<UnrealValue>,
+    ) -> Option<UnrealValue> {
+        self.components
+            .entry(component.to_owned())
+            .or_default()
+            .insert(key.to_owned(), value.into())
+    }
+}
+
+/// A read-only view of a component on a brick, created by `Brick::component`.
+#[derive(Debug, Clone, Copy)]
+pub struct ComponentView<'a> {
+    properties: &'a HashMap<String, UnrealValue>,
+}
+
+impl<'a> ComponentView<'a> {
+    /// Get the value of the property `key`, if the component has it.
+    pub fn property(&self, key: &str) -> Option<&'a UnrealValue> {
+        self.properties.get(key)
+    }
+
+    /// Iterate over the component's property names and values, in no particular order.
+    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a UnrealValue)> {
+        self.properties
+            .iter()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+/// The bricks of a save in a columnar (struct of arrays) layout.
+///
+/// Every column has one entry per brick, in the same order as `SaveData::bricks`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct BrickColumns {
+    pub asset_name_indices: Vec<u32>,
+    pub sizes: Vec<Size>,
+    pub positions: Vec<(i32, i32, i32)>,
+    pub orientations: Vec<Orientation>,
+    pub collisions: Vec<Collision>,
+    pub visibilities: Vec<bool>,
+    pub material_indices: Vec<u32>,
+    pub physical_indices: Vec<u32>,
+    pub material_intensities: Vec<u32>,
+    pub colors: Vec<BrickColor>,
+    pub owner_indices: Vec<u32>,
+    pub components: Vec<HashMap<String, HashMap<String, UnrealValue>>>,
+}
+
+impl BrickColumns {
+    /// Create a set of columns with room for `capacity` bricks.
+    pub fn with_capacity(capacity: usize) -> Self {
+        BrickColumns {
+            asset_name_indices: Vec::with_capacity(capacity),
+            sizes: Vec::with_capacity(capacity),
+            positions: Vec::with_capacity(capacity),
+            orientations: Vec::with_capacity(capacity),
+            collisions: Vec::with_capacity(capacity),
+            visibilities: Vec::with_capacity(capacity),
+            material_indices: Vec::with_capacity(capacity),
+            physical_indices: Vec::with_capacity(capacity),
+            material_intensities: Vec::with_capacity(capacity),
+            colors: Vec::with_capacity(capacity),
+            owner_indices: Vec::with_capacity(capacity),
+            components: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Split a list of bricks into columns.
+    pub fn from_bricks(bricks: &[Brick]) -> Self {
+        let mut columns = Self::with_capacity(bricks.len());
+        for brick in bricks {
+            columns.push(brick.clone());
+        }
+        columns
+    }
+
+    /// Append a brick to the end of each column.
+    pub fn push(&mut self, brick: Brick) {
+        self.asset_name_indices.push(brick.asset_name_index);
+        self.sizes.push(brick.size);
+        self.positions.push(brick.position);
+        self.orientations
+            .push(Orientation::new(brick.direction, brick.rotation));
+        self.collisions.push(brick.collision);
+        self.visibilities.push(brick.visibility);
+        self.material_indices.push(brick.material_index);
+        self.physical_indices.push(brick.physical_index);
+        self.material_intensities.push(brick.material_intensity);
+        self.colors.push(brick.color);
+        self.owner_indices.push(brick.owner_index);
+        self.components.push(brick.components);
+    }
+
+    /// The number of bricks in the columns.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Whether the columns hold no bricks.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Join the columns back into a list of bricks.
+    ///
+    /// Panics if the columns are not all the same length.
+    pub fn into_bricks(self) -> Vec<Brick> {
+        let len = self.len();
+        assert!(
+            [
+                self.asset_name_indices.len(),
+                self.sizes.len(),
+                self.orientations.len(),
+                self.collisions.len(),
+                self.visibilities.len(),
+                self.material_indices.len(),
+                self.physical_indices.len(),
+                self.material_intensities.len(),
+                self.colors.len(),
+                self.owner_indices.len(),
+                self.components.len(),
+            ]
+            .iter()
+            .all(|&l| l == len),
+            "brick columns have different lengths"
+        );
+
+        let mut asset_name_indices = self.asset_name_indices.into_iter();
+        let mut sizes = self.sizes.into_iter();
+        let mut orientations = self.orientations.into_iter();
+        let mut collisions = self.collisions.into_iter();
+        let mut visibilities = self.visibilities.into_iter();
+        let mut material_indices = self.material_indices.into_iter();
+        let mut physical_indices = self.physical_indices.into_iter();
+        let mut material_intensities = self.material_intensities.into_iter();
+        let mut colors = self.colors.into_iter();
+        let mut owner_indices = self.owner_indices.into_iter();
+        let mut components = self.components.into_iter();
+
+        self.positions
+            .into_iter()
+            .map(|position| {
+                let orientation = orientations.next().unwrap();
+                Brick {
+                    asset_name_index: asset_name_indices.next().unwrap(),
+                    size: sizes.next().unwrap(),
+                    position,
+                    direction: orientation.direction,
+                    rotation: orientation.rotation,
+                    collision: collisions.next().unwrap(),
+                    visibility: visibilities.next().unwrap(),
+                    material_index: material_indices.next().unwrap(),
+                    physical_index: physical_indices.next().unwrap(),
+                    material_intensity: material_intensities.next().unwrap(),
+                    color: colors.next().unwrap(),
+                    owner_index: owner_indices.next().unwrap(),
+                    components: components.next().unwrap(),
+                }
+            })
+            .collect()
+    }
+}
+
 impl Hash for Brick {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.asset_name_index.hash(state);
@@ -441,7 +2177,9 @@ impl Hash for Brick {
 
 /// Represents a brick's direction.
 #[repr(u8)]
-#[derive(Debug, Clone, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Clone, Copy, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 #[cfg_attr(feature = "serialize", derive(Serialize_repr, Deserialize_repr))]
 pub enum Direction {
     XPositive,
@@ -454,7 +2192,9 @@ pub enum Direction {
 
 /// Represents a brick's rotation.
 #[repr(u8)]
-#[derive(Debug, Clone, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Clone, Copy, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 #[cfg_attr(feature = "serialize", derive(Serialize_repr, Deserialize_repr))]
 pub enum Rotation {
     Deg0,
@@ -463,6 +2203,73 @@ pub enum Rotation {
     Deg270,
 }
 
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::XPositive => "+X",
+            Direction::XNegative => "-X",
+            Direction::YPositive => "+Y",
+            Direction::YNegative => "-Y",
+            Direction::ZPositive => "+Z",
+            Direction::ZNegative => "-Z",
+        })
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Rotation::Deg0 => "0°",
+            Rotation::Deg90 => "90°",
+            Rotation::Deg180 => "180°",
+            Rotation::Deg270 => "270°",
+        })
+    }
+}
+
+/// Represents a brick's orientation: the direction it faces and its rotation about that direction.
+///
+/// In a save, the orientation is packed into a single value as `direction << 2 | rotation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Orientation {
+    pub direction: Direction,
+    pub rotation: Rotation,
+}
+
+impl Orientation {
+    pub fn new(direction: Direction, rotation: Rotation) -> Self {
+        Orientation {
+            direction,
+            rotation,
+        }
+    }
+
+    /// Unpack an orientation from its packed form.
+    pub fn from_packed(packed: u32) -> Self {
+        Orientation {
+            direction: Direction::try_from(((packed >> 2) % 6) as u8).unwrap(),
+            rotation: Rotation::try_from((packed & 3) as u8).unwrap(),
+        }
+    }
+
+    /// Pack this orientation into the form stored in a save.
+    pub fn packed(self) -> u32 {
+        ((self.direction as u32) << 2) | (self.rotation as u32)
+    }
+
+    /// Describe this orientation, e.g. "facing +X, rotated 90°".
+    pub fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "facing {}, rotated {}", self.direction, self.rotation)
+    }
+}
+
 /// Represents a storable brick size.
 ///
 /// Procedural bricks should use `Size::Procedural`.
@@ -476,6 +2283,35 @@ pub enum Size {
     Procedural(u32, u32, u32),
 }
 
+impl Size {
+    /// Get the dimensions of a brick of asset `asset_name` with this size.
+    ///
+    /// Procedural sizes are returned as-is. `Size::Empty` resolves to the asset's size in
+    /// `util::BRICK_SIZE_MAP`, or to the size a default procedural asset starts out at in
+    /// `util::PROCEDURAL_SIZE_MAP`, or (0, 0, 0) if the asset isn't known.
+    #[cfg(feature = "util")]
+    pub fn resolve(&self, asset_name: &str) -> (u32, u32, u32) {
+        match crate::util::PROCEDURAL_SIZE_MAP.get(asset_name) {
+            Some(&size) if *self == Size::Empty => size,
+            _ => self.resolve_with(asset_name, &crate::util::BRICK_SIZE_MAP),
+        }
+    }
+
+    /// Like `resolve`, but looks up `Size::Empty` in `sizes` instead, e.g. a copy of
+    /// `util::BRICK_SIZE_MAP` extended with modded assets.
+    #[cfg(feature = "util")]
+    pub fn resolve_with(
+        &self,
+        asset_name: &str,
+        sizes: &HashMap<&str, (u32, u32, u32)>,
+    ) -> (u32, u32, u32) {
+        match *self {
+            Size::Procedural(x, y, z) => (x, y, z),
+            Size::Empty => sizes.get(asset_name).copied().unwrap_or((0, 0, 0)),
+        }
+    }
+}
+
 #[cfg(feature = "serialize")]
 impl Serialize for Size {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
@@ -532,7 +2368,8 @@ impl<'de> Deserialize<'de> for Size {
 /// Represents a brick's color.
 ///
 /// Bricks that refer to a color in their save should use `BrickColor::Index`.
-/// Bricks defining their own `Color` should use `BrickColor::Unique`.
+/// Bricks defining their own `Color` should use `BrickColor::Unique`. Unique colors are
+/// stored as RGB, unlike the BGRA palette, and lose their alpha (see `Color`).
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize), serde(untagged))]
 pub enum BrickColor {
@@ -571,7 +2408,7 @@ impl Default for Collision {
 }
 
 /// A component.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Component {
     pub version: i32,
@@ -579,8 +2416,11 @@ pub struct Component {
     /// The indices of bricks this component is on.
     pub brick_indices: Vec<u32>,
 
-    /// A map from property name to Unreal type (see `UnrealType`).
-    pub properties: HashMap<String, String>,
+    /// A map from property name to Unreal type name (see `UnrealValue::type_name`).
+    ///
+    /// Properties are written in the order of this map, so it is sorted to make writing the
+    /// same save always give the same bytes.
+    pub properties: BTreeMap<String, String>,
 }
 
 impl Default for Component {
@@ -588,7 +2428,7 @@ impl Default for Component {
         Component {
             version: 1,
             brick_indices: vec![],
-            properties: HashMap::new(),
+            properties: BTreeMap::new(),
         }
     }
 }
@@ -0,0 +1,183 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+use crate::save::{Brick, Component, Header1, Header2, Preview, SaveData};
+
+/// The number of bricks in each shared chunk of a `SharedSave`.
+pub const CHUNK_SIZE: usize = 4096;
+
+/// A copy-on-write save, cheap to clone.
+///
+/// Headers, the preview and the components are each held behind an `Arc`, and bricks are
+/// held in `Arc`-shared chunks of `CHUNK_SIZE`. Cloning a `SharedSave` only copies pointers;
+/// mutating it copies just the part being mutated (for bricks, the one chunk containing
+/// the brick), so the clones share everything else. This makes it suitable for undo
+/// histories and speculative edits of large builds.
+///
+/// Bricks can be edited and appended in place. For anything that shifts brick indices,
+/// convert back with `into_save_data`.
+#[derive(Debug, Clone)]
+pub struct SharedSave {
+    /// See `SaveData::save_version`.
+    pub save_version: u16,
+
+    /// See `SaveData::game_version`.
+    pub game_version: i32,
+
+    header1: Arc<Header1>,
+    header2: Arc<Header2>,
+    preview: Arc<Preview>,
+    components: Arc<HashMap<String, Component>>,
+    chunks: Vec<Arc<Vec<Brick>>>,
+    len: usize,
+}
+
+impl SharedSave {
+    /// Convert a `SaveData` into a `SharedSave`, consuming it.
+    pub fn new(data: SaveData) -> Self {
+        let len = data.bricks.len();
+        let mut bricks = data.bricks.into_iter();
+        let chunks = (0..len.div_ceil(CHUNK_SIZE))
+            .map(|_| Arc::new(bricks.by_ref().take(CHUNK_SIZE).collect()))
+            .collect();
+
+        SharedSave {
+            save_version: data.save_version,
+            game_version: data.game_version,
+            header1: Arc::new(data.header1),
+            header2: Arc::new(data.header2),
+            preview: Arc::new(data.preview),
+            components: Arc::new(data.components),
+            chunks,
+            len,
+        }
+    }
+
+    /// Convert this `SharedSave` back into a `SaveData`, copying anything still shared
+    /// with another `SharedSave`.
+    pub fn into_save_data(self) -> SaveData {
+        let mut bricks = Vec::with_capacity(self.len);
+        for chunk in self.chunks {
+            bricks.extend(Arc::try_unwrap(chunk).unwrap_or_else(|chunk| (*chunk).clone()));
+        }
+
+        SaveData {
+            save_version: self.save_version,
+            game_version: self.game_version,
+            header1: Arc::try_unwrap(self.header1).unwrap_or_else(|h| (*h).clone()),
+            header2: Arc::try_unwrap(self.header2).unwrap_or_else(|h| (*h).clone()),
+            preview: Arc::try_unwrap(self.preview).unwrap_or_else(|p| (*p).clone()),
+            components: Arc::try_unwrap(self.components).unwrap_or_else(|c| (*c).clone()),
+            bricks,
+        }
+    }
+
+    /// The number of bricks in the save.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the save has no bricks.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the brick at `index`.
+    pub fn brick(&self, index: usize) -> Option<&Brick> {
+        self.chunks
+            .get(index / CHUNK_SIZE)
+            .and_then(|chunk| chunk.get(index % CHUNK_SIZE))
+    }
+
+    /// Get the brick at `index` mutably, first copying its chunk if it is shared.
+    pub fn brick_mut(&mut self, index: usize) -> Option<&mut Brick> {
+        self.chunks
+            .get_mut(index / CHUNK_SIZE)
+            .and_then(|chunk| Arc::make_mut(chunk).get_mut(index % CHUNK_SIZE))
+    }
+
+    /// Iterate over the bricks in the save.
+    pub fn bricks(&self) -> impl Iterator<Item = &Brick> {
+        self.chunks.iter().flat_map(|chunk| chunk.iter())
+    }
+
+    /// Append a brick to the save.
+    pub fn push(&mut self, brick: Brick) {
+        match self.chunks.last_mut() {
+            Some(chunk) if chunk.len() < CHUNK_SIZE => Arc::make_mut(chunk).push(brick),
+            _ => self.chunks.push(Arc::new(vec![brick])),
+        }
+        self.len += 1;
+    }
+
+    /// The ranges of brick indices whose chunks are not shared with `other`, i.e. where
+    /// the two saves may differ. Bricks outside these ranges are identical in both.
+    pub fn diverged_ranges(&self, other: &SharedSave) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = vec![];
+        let chunks = self.chunks.len().max(other.chunks.len());
+        for i in 0..chunks {
+            let shared = match (self.chunks.get(i), other.chunks.get(i)) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                _ => false,
+            };
+            if shared {
+                continue;
+            }
+
+            let start = i * CHUNK_SIZE;
+            let end = (start + CHUNK_SIZE).min(self.len.max(other.len));
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        ranges
+    }
+
+    pub fn header1(&self) -> &Header1 {
+        &self.header1
+    }
+
+    /// Get the first header mutably, first copying it if it is shared.
+    pub fn header1_mut(&mut self) -> &mut Header1 {
+        Arc::make_mut(&mut self.header1)
+    }
+
+    pub fn header2(&self) -> &Header2 {
+        &self.header2
+    }
+
+    /// Get the second header mutably, first copying it if it is shared.
+    pub fn header2_mut(&mut self) -> &mut Header2 {
+        Arc::make_mut(&mut self.header2)
+    }
+
+    pub fn preview(&self) -> &Preview {
+        &self.preview
+    }
+
+    /// Get the preview mutably, first copying it if it is shared.
+    pub fn preview_mut(&mut self) -> &mut Preview {
+        Arc::make_mut(&mut self.preview)
+    }
+
+    pub fn components(&self) -> &HashMap<String, Component> {
+        &self.components
+    }
+
+    /// Get the components mutably, first copying them if they are shared.
+    pub fn components_mut(&mut self) -> &mut HashMap<String, Component> {
+        Arc::make_mut(&mut self.components)
+    }
+}
+
+impl From<SaveData> for SharedSave {
+    fn from(data: SaveData) -> Self {
+        SharedSave::new(data)
+    }
+}
+
+impl From<SharedSave> for SaveData {
+    fn from(shared: SharedSave) -> Self {
+        shared.into_save_data()
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::save::{Brick, BrickColor, Collision, Direction, Rotation, SaveData, Size};
+
+use super::{get_axis_size, get_scale_axis, BOX_ASSETS, MAX_PROCEDURAL_SIZE};
+
+/// Everything about a brick that must match for it to be merged with another,
+/// plus the offset of the lattice its cell sits on.
+type MergeKey = (
+    u32,
+    Size,
+    Direction,
+    Rotation,
+    Collision,
+    bool,
+    u32,
+    u32,
+    u32,
+    BrickColor,
+    u32,
+    (i32, i32, i32),
+);
+
+/// A group of same-sized, mergeable bricks: the world half extents shared by the group,
+/// and each brick's index by its cell on the group's lattice.
+struct Group {
+    half: (i32, i32, i32),
+    cells: HashMap<(i32, i32, i32), usize>,
+}
+
+pub(crate) fn greedy_merge(data: &mut SaveData) -> usize {
+    let assets = &data.header2.brick_assets;
+    let mut groups: HashMap<MergeKey, Group> = HashMap::new();
+
+    for (i, brick) in data.bricks.iter().enumerate() {
+        // only procedural boxes keep their shape when resized, and components cannot be merged
+        let is_box = assets
+            .get(brick.asset_name_index as usize)
+            .is_some_and(|asset| BOX_ASSETS.contains(&asset.as_str()));
+        if brick.size == Size::Empty || !is_box || !brick.components.is_empty() {
+            continue;
+        }
+
+        let sizes = (
+            get_axis_size(brick, assets, 0),
+            get_axis_size(brick, assets, 1),
+            get_axis_size(brick, assets, 2),
+        );
+        let valid = 1..=MAX_PROCEDURAL_SIZE;
+        if !valid.contains(&sizes.0) || !valid.contains(&sizes.1) || !valid.contains(&sizes.2) {
+            continue;
+        }
+        let half = (sizes.0 as i32, sizes.1 as i32, sizes.2 as i32);
+
+        let step = (half.0 * 2, half.1 * 2, half.2 * 2);
+        let (x, y, z) = brick.position;
+        let offset = (
+            x.rem_euclid(step.0),
+            y.rem_euclid(step.1),
+            z.rem_euclid(step.2),
+        );
+        let cell = (
+            x.div_euclid(step.0),
+            y.div_euclid(step.1),
+            z.div_euclid(step.2),
+        );
+
+        let key = (
+            brick.asset_name_index,
+            brick.size.clone(),
+            brick.direction,
+            brick.rotation,
+            brick.collision.clone(),
+            brick.visibility,
+            brick.material_index,
+            brick.physical_index,
+            brick.material_intensity,
+            brick.color.clone(),
+            brick.owner_index,
+            offset,
+        );
+
+        // overlapping duplicates keep the first brick in the cell, the rest are left alone
+        groups
+            .entry(key)
+            .or_insert_with(|| Group {
+                half,
+                cells: HashMap::new(),
+            })
+            .cells
+            .entry(cell)
+            .or_insert(i);
+    }
+
+    // the merged brick replacing each anchor brick, and the bricks merged into another
+    let mut replacements: HashMap<usize, Brick> = HashMap::new();
+    let mut removed: HashSet<usize> = HashSet::new();
+
+    for group in groups.values() {
+        let mut cells = group.cells.keys().copied().collect::<Vec<_>>();
+        cells.sort_unstable_by_key(|&(x, y, z)| (z, y, x));
+
+        let mut visited: HashSet<(i32, i32, i32)> = HashSet::new();
+
+        // how many cells fit along each axis before the merged brick is too large
+        let max = MAX_PROCEDURAL_SIZE as i32;
+        let limit = (max / group.half.0, max / group.half.1, max / group.half.2);
+        let free = |cell: (i32, i32, i32), visited: &HashSet<(i32, i32, i32)>| {
+            group.cells.contains_key(&cell) && !visited.contains(&cell)
+        };
+
+        for &(x, y, z) in cells.iter() {
+            if visited.contains(&(x, y, z)) {
+                continue;
+            }
+
+            // grow along x, then whole rows along y, then whole layers along z
+            let mut w = 1;
+            while w < limit.0 && free((x + w, y, z), &visited) {
+                w += 1;
+            }
+            let mut h = 1;
+            while h < limit.1 && (0..w).all(|dx| free((x + dx, y + h, z), &visited)) {
+                h += 1;
+            }
+            let mut d = 1;
+            while d < limit.2
+                && (0..w).all(|dx| (0..h).all(|dy| free((x + dx, y + dy, z + d), &visited)))
+            {
+                d += 1;
+            }
+
+            let mut indices = vec![];
+            for dz in 0..d {
+                for dy in 0..h {
+                    for dx in 0..w {
+                        visited.insert((x + dx, y + dy, z + dz));
+                        indices.push(group.cells[&(x + dx, y + dy, z + dz)]);
+                    }
+                }
+            }
+
+            if indices.len() == 1 {
+                continue;
+            }
+
+            let first = &data.bricks[group.cells[&(x, y, z)]];
+            let half = (group.half.0 * w, group.half.1 * h, group.half.2 * d);
+
+            // map the world extents back onto the brick's own axes
+            let mut size = [0u32; 3];
+            for (axis, extent) in [half.0, half.1, half.2].iter().enumerate() {
+                size[get_scale_axis(first, axis as u8) as usize] = *extent as u32;
+            }
+
+            let merged = Brick {
+                size: Size::Procedural(size[0], size[1], size[2]),
+                position: (
+                    first.position.0 + (w - 1) * group.half.0,
+                    first.position.1 + (h - 1) * group.half.1,
+                    first.position.2 + (d - 1) * group.half.2,
+                ),
+                ..first.clone()
+            };
+
+            let anchor = *indices.iter().min().unwrap();
+            removed.extend(indices.into_iter().filter(|&i| i != anchor));
+            replacements.insert(anchor, merged);
+        }
+    }
+
+    let before = data.bricks.len();
+    data.bricks = std::mem::take(&mut data.bricks)
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !removed.contains(i))
+        .map(|(i, brick)| replacements.remove(&i).unwrap_or(brick))
+        .collect();
+    data.header1.brick_count = data.bricks.len() as u32;
+    data.recount_owners();
+    data.reconcile_components();
+
+    before - data.bricks.len()
+}
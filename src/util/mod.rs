@@ -1,10 +1,15 @@
+pub(crate) mod merge;
 pub mod octree;
+pub mod palette;
+pub mod perf;
+pub(crate) mod split;
+pub mod voxel;
 
 use std::collections::HashMap;
 
 use lazy_static::lazy_static;
 
-use crate::save::{Brick, Direction, Rotation, Size};
+use crate::save::{Bounds, Brick, Direction, Orientation, Rotation, Size};
 
 pub const ROTATION_TABLE: [u8; 576] = [
     16, 15, 22, 9, 18, 11, 20, 13, 17, 3, 21, 5, 19, 7, 23, 1, 0, 8, 4, 12, 6, 10, 2, 14, 17, 12,
@@ -75,6 +80,10 @@ lazy_static! {
         "BMC_Metallic",
         "BMC_Hologram",
     ];
+    /// The sizes of known static mesh brick assets, by asset name.
+    ///
+    /// To resolve sizes for modded assets, clone this map, insert their sizes, and pass it to
+    /// `Size::resolve_with`.
     pub static ref BRICK_SIZE_MAP: HashMap<&'static str, (u32, u32, u32)> = vec![
         ("B_1x1_Brick_Side", (5, 5, 6)),
         ("B_1x1_Brick_Side_Lip", (5, 5, 6)),
@@ -154,8 +163,41 @@ lazy_static! {
     ]
     .into_iter()
     .collect::<HashMap<_, _>>();
+    /// The sizes the default procedural brick assets start out at when placed, by asset name.
+    ///
+    /// Procedural bricks store their own size, so this is only used by `Size::resolve` for
+    /// procedural assets given `Size::Empty`.
+    pub static ref PROCEDURAL_SIZE_MAP: HashMap<&'static str, (u32, u32, u32)> = vec![
+        ("PB_DefaultBrick", (5, 5, 6)),
+        ("PB_DefaultMicroBrick", (1, 1, 1)),
+        ("PB_DefaultMicroWedge", (1, 1, 1)),
+        ("PB_DefaultPole", (5, 5, 6)),
+        ("PB_DefaultRamp", (5, 5, 6)),
+        ("PB_DefaultRampCorner", (5, 5, 6)),
+        ("PB_DefaultRampCrest", (5, 5, 6)),
+        ("PB_DefaultRampInnerCorner", (5, 5, 6)),
+        ("PB_DefaultSideWedge", (5, 5, 6)),
+        ("PB_DefaultSideWedgeTile", (5, 5, 2)),
+        ("PB_DefaultSmoothTile", (5, 5, 2)),
+        ("PB_DefaultTile", (5, 5, 2)),
+        ("PB_DefaultWedge", (5, 5, 6)),
+    ]
+    .into_iter()
+    .collect::<HashMap<_, _>>();
 }
 
+/// The largest half extent the game allows along any axis of a procedural brick.
+pub const MAX_PROCEDURAL_SIZE: u32 = 500;
+
+/// The procedural brick assets that are plain boxes, so that bricks placed side by side can be
+/// replaced by one larger brick without changing their shape.
+pub const BOX_ASSETS: [&str; 4] = [
+    "PB_DefaultBrick",
+    "PB_DefaultTile",
+    "PB_DefaultMicroBrick",
+    "PB_DefaultSmoothTile",
+];
+
 #[inline]
 fn translation_coord(coords: (i32, i32, i32), translation: i8) -> i32 {
     let sign = translation.signum() as i32;
@@ -183,7 +225,7 @@ pub fn get_brick_size(brick: &Brick, assets: &[String]) -> (u32, u32, u32) {
     assets
         .get(brick.asset_name_index as usize)
         .and_then(|a| BRICK_SIZE_MAP.get(a.as_str()))
-        .map(|s| *s)
+        .copied()
         .unwrap_or((0, 0, 0))
 }
 
@@ -230,6 +272,42 @@ pub fn get_axis_size(brick: &Brick, assets: &[String], axis: u8) -> u32 {
     }
 }
 
+/// Gets the bounds of a brick as its minimum and maximum corners, accounting for its
+/// orientation. Non-procedural bricks whose asset size is unknown are treated as a point.
+/// Corners beyond the range of an `i32` are clamped to it.
+pub fn get_brick_bounds(brick: &Brick, assets: &[String]) -> Bounds {
+    let size = |axis| i64::from(get_axis_size(brick, assets, axis));
+    let clamp = |v: i64| v.clamp(i32::MIN.into(), i32::MAX.into()) as i32;
+    let corner = |sign: i64| {
+        let p = brick.position;
+        (
+            clamp(i64::from(p.0) + sign * size(0)),
+            clamp(i64::from(p.1) + sign * size(1)),
+            clamp(i64::from(p.2) + sign * size(2)),
+        )
+    };
+    (corner(-1), corner(1))
+}
+
+/// Rotate an orientation about the Z axis by `quarter_turns` quarter turns, counterclockwise
+/// when viewed from above (so +X turns toward +Y).
+pub fn rotate_orientation_z(orientation: Orientation, quarter_turns: u8) -> Orientation {
+    let rotate = |mut v: (i32, i32, i32)| {
+        for _ in 0..quarter_turns % 4 {
+            v = (-v.1, v.0, v.2);
+        }
+        v
+    };
+
+    // find the orientation that maps the brick's axes to where the rotation takes them
+    let probe = (1, 2, 3);
+    let target = rotate(use_translation_table(probe, orientation.packed() as u8));
+    (0..24)
+        .find(|&o| use_translation_table(probe, o) == target)
+        .map(|o| Orientation::from_packed(o as u32))
+        .unwrap()
+}
+
 pub mod rotation {
     pub fn d2o(direction: u8, rotation: u8) -> u8 {
         (direction << 2) | rotation
@@ -5,7 +5,7 @@ use std::hash::Hash;
 
 use crate::save::{Brick, Direction, SaveData};
 
-use super::get_axis_size;
+use super::{get_axis_size, get_brick_bounds};
 
 pub const CHUNK_SIZE: i32 = 1024;
 pub const RIGHT: i32 = 1;
@@ -263,6 +263,12 @@ pub struct ChunkTree<T: PartialEq + Eq + Hash + Copy> {
     pub chunks: Vec<(Node<T>, Point)>,
 }
 
+impl<T: PartialEq + Eq + Hash + Copy> Default for ChunkTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: PartialEq + Eq + Hash + Copy> ChunkTree<T> {
     pub fn new() -> Self {
         ChunkTree { chunks: vec![] }
@@ -415,19 +421,7 @@ impl SaveOctree {
 
     /// Gets the bounds of a brick as two points in space.
     pub fn brick_bounds(&self, brick: &Brick) -> ((i32, i32, i32), (i32, i32, i32)) {
-        let s = self.brick_size(brick);
-        (
-            (
-                brick.position.0 - s.0 as i32,
-                brick.position.1 - s.1 as i32,
-                brick.position.2 - s.2 as i32,
-            ),
-            (
-                brick.position.0 + s.0 as i32,
-                brick.position.1 + s.1 as i32,
-                brick.position.2 + s.2 as i32,
-            ),
-        )
+        get_brick_bounds(brick, &self.data.header2.brick_assets)
     }
 
     /// Fetch all bricks within some volume in space. This includes bricks that are partially
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::save::{BrickColor, Color, SaveData};
+
+/// The result of `SaveData::palettize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PalettizeReport {
+    /// How many colors were added to the palette.
+    pub colors_added: usize,
+
+    /// How many bricks were converted from `BrickColor::Unique` to `BrickColor::Index`.
+    pub bricks_converted: usize,
+
+    /// An estimate of how many bytes the save shrank by, counting the color bits of every
+    /// brick and the palette entries in header 2. Per-brick byte alignment is not accounted for.
+    pub bytes_saved: i64,
+}
+
+/// Reduce `colors`, each paired with a weight, to at most `max_colors` representative colors
+/// using median cut.
+pub fn median_cut(colors: &[(Color, usize)], max_colors: usize) -> Vec<Color> {
+    if colors.is_empty() || max_colors == 0 {
+        return vec![];
+    }
+
+    fn channel(color: &Color, c: usize) -> u8 {
+        match c {
+            0 => color.r,
+            1 => color.g,
+            _ => color.b,
+        }
+    }
+
+    // the (channel, range) of a box's widest channel
+    fn widest(colors: &[(Color, usize)]) -> (usize, u8) {
+        (0..3)
+            .map(|c| {
+                let min = colors.iter().map(|(color, _)| channel(color, c)).min();
+                let max = colors.iter().map(|(color, _)| channel(color, c)).max();
+                (c, max.unwrap_or(0) - min.unwrap_or(0))
+            })
+            .max_by_key(|&(_, range)| range)
+            .unwrap()
+    }
+
+    let mut boxes = vec![colors.to_vec()];
+    while boxes.len() < max_colors {
+        let (index, (c, range)) = match boxes
+            .iter()
+            .map(|b| widest(b))
+            .enumerate()
+            .max_by_key(|&(_, (_, range))| range)
+        {
+            Some(widest) => widest,
+            None => break,
+        };
+
+        // every box is a single color already
+        if range == 0 {
+            break;
+        }
+
+        let mut split = boxes.swap_remove(index);
+        split.sort_by_key(|(color, _)| channel(color, c));
+
+        // split at the weighted median, keeping at least one color on each side
+        let total = split.iter().map(|&(_, w)| w).sum::<usize>();
+        let mut acc = 0;
+        let mut at = 1;
+        for (i, &(_, w)) in split.iter().enumerate() {
+            acc += w;
+            if acc * 2 >= total {
+                at = i + 1;
+                break;
+            }
+        }
+        let at = at.clamp(1, split.len() - 1);
+
+        let upper = split.split_off(at);
+        boxes.push(split);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|b| {
+            let total = b.iter().map(|&(_, w)| w.max(1) as u64).sum::<u64>();
+            let avg = |f: fn(&Color) -> u8| -> u8 {
+                let sum = b
+                    .iter()
+                    .map(|&(ref color, w)| f(color) as u64 * w.max(1) as u64)
+                    .sum::<u64>();
+                ((sum + total / 2) / total) as u8
+            };
+            Color {
+                r: avg(|c| c.r),
+                g: avg(|c| c.g),
+                b: avg(|c| c.b),
+                a: avg(|c| c.a),
+            }
+        })
+        .collect()
+}
+
+/// Find the index of the color in `palette` nearest to `color`, ignoring alpha.
+pub fn nearest(palette: &[Color], color: &Color) -> Option<usize> {
+    let distance = |other: &Color| -> i32 {
+        let (dr, dg, db) = (
+            color.r as i32 - other.r as i32,
+            color.g as i32 - other.g as i32,
+            color.b as i32 - other.b as i32,
+        );
+        dr * dr + dg * dg + db * db
+    };
+
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, other)| distance(other))
+        .map(|(i, _)| i)
+}
+
+/// A palette quantized from an image, and the palette index of each of its pixels. Created by
+/// `SaveData::palette_from_image`.
+#[cfg(feature = "image")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePalette {
+    /// The palette, ready to be used as `Header2::colors`.
+    pub colors: Vec<Color>,
+
+    /// The width of the image, in pixels.
+    pub width: u32,
+
+    /// The height of the image, in pixels.
+    pub height: u32,
+
+    /// The index into `colors` of each pixel, row by row from the top left.
+    pub indices: Vec<u32>,
+}
+
+#[cfg(feature = "image")]
+impl ImagePalette {
+    /// Get the palette index of the pixel at `(x, y)`, or `None` if it is out of bounds.
+    pub fn index(&self, x: u32, y: u32) -> Option<u32> {
+        if x < self.width && y < self.height {
+            Some(self.indices[(y * self.width + x) as usize])
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+pub(crate) fn palette_from_image(image: &image::RgbaImage, max_colors: usize) -> ImagePalette {
+    let mut counts: HashMap<Color, usize> = HashMap::new();
+    for pixel in image.pixels() {
+        let [r, g, b, a] = pixel.0;
+        *counts.entry(Color { r, g, b, a }).or_default() += 1;
+    }
+
+    let colors = median_cut(&counts.into_iter().collect::<Vec<_>>(), max_colors);
+
+    // images have far fewer distinct colors than pixels, so look each one up once
+    let mut lookup: HashMap<Color, u32> = HashMap::new();
+    let indices = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            let color = Color { r, g, b, a };
+            *lookup
+                .entry(color)
+                .or_insert_with_key(|color| nearest(&colors, color).unwrap_or(0) as u32)
+        })
+        .collect();
+
+    ImagePalette {
+        colors,
+        width: image.width(),
+        height: image.height(),
+        indices,
+    }
+}
+
+/// The number of bits `BitWriteExt::write_uint` uses to write `value` with a maximum of `max`.
+fn uint_bits(value: u32, max: u32) -> i64 {
+    let (mut new_value, mut mask, mut bits) = (0u32, 1u32, 0);
+    while new_value + mask < max && mask != 0 {
+        if value & mask != 0 {
+            new_value |= mask;
+        }
+        mask <<= 1;
+        bits += 1;
+    }
+    bits
+}
+
+/// The number of bits a brick color takes up in the brick section.
+fn color_bits(color: &BrickColor, color_count: usize) -> i64 {
+    1 + match color {
+        BrickColor::Index(index) => uint_bits(*index, color_count.max(2) as u32),
+        BrickColor::Unique(_) => 24,
+    }
+}
+
+pub(crate) fn palettize(data: &mut SaveData, max_colors: usize) -> PalettizeReport {
+    let before_count = data.header2.colors.len();
+    let before_bits = data
+        .bricks
+        .iter()
+        .map(|b| color_bits(&b.color, before_count))
+        .sum::<i64>();
+
+    // collect the unique colors and how many bricks use each, ignoring alpha
+    // as unique brick colors are stored as rgb
+    let mut weights: HashMap<Color, usize> = HashMap::new();
+    for brick in data.bricks.iter() {
+        if let BrickColor::Unique(color) = &brick.color {
+            *weights
+                .entry(Color {
+                    a: 255,
+                    ..color.clone()
+                })
+                .or_insert(0) += 1;
+        }
+    }
+
+    if weights.is_empty() {
+        return PalettizeReport::default();
+    }
+
+    let budget = max_colors.saturating_sub(before_count);
+    let mut weights = weights.into_iter().collect::<Vec<_>>();
+    weights.sort_by_key(|(color, _)| (color.r, color.g, color.b));
+    let added = if weights.len() <= budget {
+        weights.iter().map(|(color, _)| color.clone()).collect()
+    } else {
+        median_cut(&weights, budget)
+    };
+
+    data.header2.colors.extend(added.iter().cloned());
+    if data.header2.colors.is_empty() {
+        return PalettizeReport::default();
+    }
+
+    let mut bricks_converted = 0;
+    let mut lookup: HashMap<Color, u32> = HashMap::new();
+    let palette = &data.header2.colors;
+    for brick in data.bricks.iter_mut() {
+        if let BrickColor::Unique(color) = &brick.color {
+            let color = Color {
+                a: 255,
+                ..color.clone()
+            };
+            let index = *lookup
+                .entry(color.clone())
+                .or_insert_with(|| nearest(palette, &color).unwrap() as u32);
+            brick.color = BrickColor::Index(index);
+            bricks_converted += 1;
+        }
+    }
+
+    let after_count = data.header2.colors.len();
+    let after_bits = data
+        .bricks
+        .iter()
+        .map(|b| color_bits(&b.color, after_count))
+        .sum::<i64>();
+
+    PalettizeReport {
+        colors_added: added.len(),
+        bricks_converted,
+        bytes_saved: (before_bits - after_bits) / 8 - 4 * added.len() as i64,
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+
+use crate::save::{BrickColor, SaveData};
+
+/// The cost of a single brick.
+pub const BRICK_COST: f64 = 1.0;
+
+/// The additional cost of a brick using a glowing material.
+pub const GLOW_COST: f64 = 4.0;
+
+/// The additional cost of a brick using a translucent material.
+pub const TRANSLUCENT_COST: f64 = 2.0;
+
+/// The cost of each distinct unique (non-palette) color, which defeats batching.
+pub const UNIQUE_COLOR_COST: f64 = 0.5;
+
+/// The cost of each component attached to a brick.
+pub const COMPONENT_COST: f64 = 8.0;
+
+/// Materials that glow.
+pub const GLOW_MATERIALS: [&str; 1] = ["BMC_Glow"];
+
+/// Materials that are translucent.
+pub const TRANSLUCENT_MATERIALS: [&str; 4] =
+    ["BMC_Glass", "BMC_Hologram", "BMC_Ghost", "BMC_Ghost_Fail"];
+
+/// One factor contributing to a `PerfScore`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PerfFactor {
+    /// How many times this factor occurs in the save.
+    pub count: usize,
+
+    /// The weighted cost of this factor.
+    pub cost: f64,
+}
+
+impl PerfFactor {
+    fn new(count: usize, weight: f64) -> Self {
+        PerfFactor {
+            count,
+            cost: count as f64 * weight,
+        }
+    }
+}
+
+/// A heuristic estimate of how expensive a save is to load and render.
+///
+/// The score is opinionated: it is only meant to compare builds against each other
+/// or against a threshold, not to predict frame times.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PerfScore {
+    /// The bricks in the save.
+    pub bricks: PerfFactor,
+
+    /// The bricks using a glowing material.
+    pub glow: PerfFactor,
+
+    /// The bricks using a translucent material.
+    pub translucent: PerfFactor,
+
+    /// The distinct unique colors in the save.
+    pub unique_colors: PerfFactor,
+
+    /// The components attached to bricks in the save.
+    pub components: PerfFactor,
+}
+
+impl PerfScore {
+    /// Compute the score of a save.
+    pub fn new(data: &SaveData) -> Self {
+        let usage = data.material_usage();
+        let count_of = |materials: &[&str]| -> usize {
+            materials
+                .iter()
+                .map(|m| usage.get(m).copied().unwrap_or(0))
+                .sum()
+        };
+
+        let unique_colors = data
+            .bricks
+            .iter()
+            .filter_map(|b| match &b.color {
+                BrickColor::Unique(color) => Some(color),
+                BrickColor::Index(_) => None,
+            })
+            .collect::<HashSet<_>>()
+            .len();
+
+        let components = data.bricks.iter().map(|b| b.components.len()).sum();
+
+        PerfScore {
+            bricks: PerfFactor::new(data.bricks.len(), BRICK_COST),
+            glow: PerfFactor::new(count_of(&GLOW_MATERIALS), GLOW_COST),
+            translucent: PerfFactor::new(count_of(&TRANSLUCENT_MATERIALS), TRANSLUCENT_COST),
+            unique_colors: PerfFactor::new(unique_colors, UNIQUE_COLOR_COST),
+            components: PerfFactor::new(components, COMPONENT_COST),
+        }
+    }
+
+    /// The total score, the sum of every factor's cost.
+    pub fn total(&self) -> f64 {
+        self.bricks.cost
+            + self.glow.cost
+            + self.translucent.cost
+            + self.unique_colors.cost
+            + self.components.cost
+    }
+}
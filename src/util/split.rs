@@ -0,0 +1,121 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::save::{owner_position, Brick, BrickColor, Header1, Header2, SaveData};
+
+use super::get_axis_size;
+
+/// Maps indices into one table to indices into a smaller table holding only the entries used.
+struct Remap<'a, T: Clone> {
+    source: &'a [T],
+    indices: HashMap<u32, u32>,
+    table: Vec<T>,
+}
+
+impl<'a, T: Clone> Remap<'a, T> {
+    fn new(source: &'a [T]) -> Self {
+        Remap {
+            source,
+            indices: HashMap::new(),
+            table: vec![],
+        }
+    }
+
+    /// Map `index` into the new table, copying the entry over the first time it is seen.
+    /// Indices out of range of the source table map to 0.
+    fn map(&mut self, index: u32) -> u32 {
+        let source = self.source;
+        let table = &mut self.table;
+        *self
+            .indices
+            .entry(index)
+            .or_insert_with(|| match source.get(index as usize) {
+                Some(entry) => {
+                    table.push(entry.clone());
+                    table.len() as u32 - 1
+                }
+                None => 0,
+            })
+    }
+}
+
+/// Copy the bricks of `data` at `indices` into a standalone save, keeping only the table
+/// entries those bricks use and the components they carry.
+fn subset(data: &SaveData, indices: &[usize]) -> SaveData {
+    let mut assets = Remap::new(&data.header2.brick_assets);
+    let mut materials = Remap::new(&data.header2.materials);
+    let mut physical_materials = Remap::new(&data.header2.physical_materials);
+    let mut colors = Remap::new(&data.header2.colors);
+    let mut owners = Remap::new(&data.header2.brick_owners);
+
+    let bricks = indices
+        .iter()
+        .map(|&i| {
+            let brick = &data.bricks[i];
+            Brick {
+                asset_name_index: assets.map(brick.asset_name_index),
+                material_index: materials.map(brick.material_index),
+                physical_index: physical_materials.map(brick.physical_index),
+                color: match &brick.color {
+                    BrickColor::Index(index) => BrickColor::Index(colors.map(*index)),
+                    unique => unique.clone(),
+                },
+                owner_index: owner_position(brick.owner_index)
+                    .map_or(0, |owner| owners.map(owner as u32) + 1),
+                ..brick.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let components = bricks
+        .iter()
+        .flat_map(|brick| brick.components.keys())
+        .filter_map(|name| {
+            data.components
+                .get(name)
+                .map(|component| (name.clone(), component.clone()))
+        })
+        .collect();
+
+    let mut save = SaveData {
+        save_version: data.save_version,
+        game_version: data.game_version,
+        header1: Header1 {
+            brick_count: bricks.len() as u32,
+            ..data.header1.clone()
+        },
+        header2: Header2 {
+            mods: data.header2.mods.clone(),
+            brick_assets: assets.table,
+            colors: colors.table,
+            materials: materials.table,
+            brick_owners: owners.table,
+            physical_materials: physical_materials.table,
+            raw_extra: data.header2.raw_extra.clone(),
+        },
+        preview: data.preview.clone(),
+        bricks,
+        components,
+    };
+    save.recount_owners();
+    save.reconcile_components();
+    save
+}
+
+pub(crate) fn split_by_z(data: &SaveData, band_height: i32) -> Vec<SaveData> {
+    assert!(band_height > 0, "band_height must be positive");
+
+    let mut bands: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    for (i, brick) in data.bricks.iter().enumerate() {
+        let half_height = get_axis_size(brick, &data.header2.brick_assets, 2) as i32;
+        let base = brick.position.2 - half_height;
+        bands
+            .entry(base.div_euclid(band_height))
+            .or_default()
+            .push(i);
+    }
+
+    bands
+        .values()
+        .map(|indices| subset(data, indices))
+        .collect()
+}
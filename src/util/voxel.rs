@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use crate::save::{Brick, BrickColor, Color, Header1, Header2, SaveData, Size};
+
+/// A dense 3D grid of colored voxels, to be converted into bricks with
+/// `SaveData::from_voxel_grid`.
+///
+/// Coordinates map to the game as follows:
+///
+/// * The grid's x, y and z axes are the game's X, Y and Z axes, with Z pointing up.
+/// * `origin` is the world position, in game units, of the minimum corner of cell `(0, 0, 0)`.
+/// * `cell_size` is the full size of each cell along each axis, in game units. A stud is 10
+///   units wide and a plate 4 units tall, so `(10, 10, 4)` makes every voxel a 1x1 plate and
+///   `(10, 10, 12)` a 1x1 brick.
+///
+/// The cell `(x, y, z)` therefore becomes a brick centered at
+/// `origin + (x, y, z) * cell_size + cell_size / 2`.
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    dimensions: (usize, usize, usize),
+    origin: (i32, i32, i32),
+    cell_size: (u32, u32, u32),
+    cells: Vec<Option<Color>>,
+}
+
+impl VoxelGrid {
+    /// Create an empty grid of `dimensions` cells.
+    ///
+    /// Panics if any part of `cell_size` is not a positive, even number, as bricks are sized
+    /// by their half extents, or if the grid reaches past the largest position a brick can
+    /// be at.
+    pub fn new(
+        dimensions: (usize, usize, usize),
+        origin: (i32, i32, i32),
+        cell_size: (u32, u32, u32),
+    ) -> Self {
+        for &size in [cell_size.0, cell_size.1, cell_size.2].iter() {
+            assert!(
+                size > 0 && size % 2 == 0,
+                "cell sizes must be positive and even"
+            );
+        }
+
+        // every cell's center is short of the grid's maximum corner, so bricks' positions
+        // can't overflow if it fits
+        let axes = [
+            (dimensions.0, origin.0, cell_size.0),
+            (dimensions.1, origin.1, cell_size.1),
+            (dimensions.2, origin.2, cell_size.2),
+        ];
+        for &(cells, origin, size) in axes.iter() {
+            let max = origin as i128 + cells as i128 * size as i128;
+            assert!(max <= i32::MAX as i128, "voxel grid is too large");
+        }
+
+        let len = dimensions
+            .0
+            .checked_mul(dimensions.1)
+            .and_then(|n| n.checked_mul(dimensions.2))
+            .expect("voxel grid is too large");
+        VoxelGrid {
+            dimensions,
+            origin,
+            cell_size,
+            cells: vec![None; len],
+        }
+    }
+
+    /// The number of cells along each axis.
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        self.dimensions
+    }
+
+    /// The world position of the minimum corner of cell `(0, 0, 0)`.
+    pub fn origin(&self) -> (i32, i32, i32) {
+        self.origin
+    }
+
+    /// The full size of each cell along each axis, in game units.
+    pub fn cell_size(&self) -> (u32, u32, u32) {
+        self.cell_size
+    }
+
+    fn index(&self, (x, y, z): (usize, usize, usize)) -> Option<usize> {
+        let (w, d, h) = self.dimensions;
+        if x < w && y < d && z < h {
+            Some(x + y * w + z * w * d)
+        } else {
+            None
+        }
+    }
+
+    /// Get the color of the cell at `position`, or `None` if it is empty or out of bounds.
+    pub fn get(&self, position: (usize, usize, usize)) -> Option<&Color> {
+        self.index(position).and_then(|i| self.cells[i].as_ref())
+    }
+
+    /// Set the cell at `position`, clearing it if `color` is `None`.
+    ///
+    /// Panics if `position` is out of bounds.
+    pub fn set(&mut self, position: (usize, usize, usize), color: Option<Color>) {
+        let i = self.index(position).expect("voxel position out of bounds");
+        self.cells[i] = color;
+    }
+
+    /// Iterate over the filled cells and their colors, in x, then y, then z order.
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize, usize), &Color)> {
+        let (w, d, _) = self.dimensions;
+        self.cells.iter().enumerate().filter_map(move |(i, cell)| {
+            cell.as_ref()
+                .map(|color| ((i % w, i / w % d, i / (w * d)), color))
+        })
+    }
+
+    /// The number of filled cells.
+    pub fn filled(&self) -> usize {
+        self.cells.iter().filter(|cell| cell.is_some()).count()
+    }
+}
+
+/// The world coordinate of the center of cell `cell` along an axis. `VoxelGrid::new` checks
+/// that this fits in an `i32`.
+fn center(origin: i32, cell: usize, size: u32) -> i32 {
+    (origin as i64 + cell as i64 * size as i64 + (size / 2) as i64) as i32
+}
+
+pub(crate) fn from_voxel_grid(grid: &VoxelGrid) -> SaveData {
+    let (sx, sy, sz) = grid.cell_size;
+    let (ox, oy, oz) = grid.origin;
+
+    let mut data = SaveData::default();
+    data.reserve_bricks(grid.filled());
+
+    let mut palette: HashMap<&Color, u32> = HashMap::new();
+    let mut colors = vec![];
+
+    for ((x, y, z), color) in grid.iter() {
+        let index = *palette.entry(color).or_insert_with(|| {
+            colors.push(color.clone());
+            colors.len() as u32 - 1
+        });
+
+        data.bricks.push(Brick {
+            size: Size::Procedural(sx / 2, sy / 2, sz / 2),
+            position: (center(ox, x, sx), center(oy, y, sy), center(oz, z, sz)),
+            color: BrickColor::Index(index),
+            ..Default::default()
+        });
+    }
+
+    data.header1 = Header1 {
+        brick_count: data.bricks.len() as u32,
+        ..Default::default()
+    };
+    data.header2 = Header2 {
+        colors,
+        ..Default::default()
+    };
+    data
+}
@@ -0,0 +1,268 @@
+use std::collections::{BTreeSet, HashMap};
+
+use thiserror::Error;
+
+use crate::save::{BrickColor, SaveData, UnrealValue};
+
+/// The kind of feature an `Incompatibility` refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FeatureKind {
+    Material,
+    Asset,
+    Component,
+}
+
+/// A feature a save uses that postdates the game version it was checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incompatibility {
+    /// The kind of the feature.
+    pub kind: FeatureKind,
+
+    /// The name of the material, brick asset, or component.
+    pub name: String,
+
+    /// The game version the feature was introduced in.
+    pub introduced: i32,
+}
+
+/// A table of the game versions in which materials, brick assets and components were introduced.
+///
+/// Features missing from the table are assumed to exist in every game version. No table is
+/// built in, as the game versions features were introduced in are not recorded in saves. See
+/// `SaveData::validate_with_table`.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureTable {
+    pub materials: HashMap<String, i32>,
+    pub assets: HashMap<String, i32>,
+    pub components: HashMap<String, i32>,
+}
+
+impl FeatureTable {
+    /// Get the game version a feature was introduced in, if it is in the table.
+    pub fn introduced(&self, kind: FeatureKind, name: &str) -> Option<i32> {
+        match kind {
+            FeatureKind::Material => self.materials.get(name),
+            FeatureKind::Asset => self.assets.get(name),
+            FeatureKind::Component => self.components.get(name),
+        }
+        .copied()
+    }
+
+    /// Check the features used by the bricks of `data` against `game_version`.
+    ///
+    /// Only features that are actually used by a brick are checked, so unused
+    /// entries in `Header2`'s arrays do not produce incompatibilities.
+    pub fn check(&self, data: &SaveData, game_version: i32) -> Vec<Incompatibility> {
+        let mut used = BTreeSet::new();
+        for brick in data.bricks.iter() {
+            if let Some(material) = data.header2.materials.get(brick.material_index as usize) {
+                used.insert((FeatureKind::Material, material.as_str()));
+            }
+            if let Some(asset) = data
+                .header2
+                .brick_assets
+                .get(brick.asset_name_index as usize)
+            {
+                used.insert((FeatureKind::Asset, asset.as_str()));
+            }
+            for component in brick.components.keys() {
+                used.insert((FeatureKind::Component, component.as_str()));
+            }
+        }
+
+        used.into_iter()
+            .filter_map(|(kind, name)| match self.introduced(kind, name) {
+                Some(introduced) if introduced > game_version => Some(Incompatibility {
+                    kind,
+                    name: name.to_owned(),
+                    introduced,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A problem with a save that would make it invalid to write. See `SaveData::validate`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("brick {brick} has {field} {index}, but there are only {len} entries")]
+    IndexOutOfRange {
+        /// The index of the offending brick.
+        brick: usize,
+
+        /// The name of the offending field on the brick.
+        field: &'static str,
+
+        /// The out of range index.
+        index: u32,
+
+        /// The length of the table the index refers to.
+        len: usize,
+    },
+    #[error("brick {brick} has component {component:?}, which is not described in the save")]
+    UnknownComponent {
+        /// The index of the offending brick.
+        brick: usize,
+
+        /// The name of the component.
+        component: String,
+    },
+    #[error("brick {brick} is missing property {property:?} of component {component:?}")]
+    MissingProperty {
+        /// The index of the offending brick.
+        brick: usize,
+
+        /// The name of the component.
+        component: String,
+
+        /// The name of the missing property.
+        property: String,
+    },
+    #[error("brick {brick} has a {found} for property {property:?} of component {component:?}, which is a {expected}")]
+    PropertyTypeMismatch {
+        /// The index of the offending brick.
+        brick: usize,
+
+        /// The name of the component.
+        component: String,
+
+        /// The name of the property.
+        property: String,
+
+        /// The property's type, as described in the save's components.
+        expected: String,
+
+        /// The type of the brick's value (see `UnrealValue::type_name`).
+        found: &'static str,
+    },
+    #[error(
+        "brick {brick} has a non-finite float for property {property:?} of component {component:?}"
+    )]
+    NonFiniteFloat {
+        /// The index of the offending brick.
+        brick: usize,
+
+        /// The name of the component.
+        component: String,
+
+        /// The name of the property.
+        property: String,
+    },
+}
+
+/// Check that every brick's indices refer to entries in the save's header tables, and that
+/// its component values match the save's components.
+pub(crate) fn validate(data: &SaveData) -> Result<(), ValidationError> {
+    let header2 = &data.header2;
+
+    for (i, brick) in data.bricks.iter().enumerate() {
+        let check = |field, index: u32, len: usize| {
+            if index as usize >= len {
+                Err(ValidationError::IndexOutOfRange {
+                    brick: i,
+                    field,
+                    index,
+                    len,
+                })
+            } else {
+                Ok(())
+            }
+        };
+
+        check(
+            "asset_name_index",
+            brick.asset_name_index,
+            header2.brick_assets.len(),
+        )?;
+        check(
+            "material_index",
+            brick.material_index,
+            header2.materials.len(),
+        )?;
+        // saves before version 9 have no physical materials, and an empty table is written
+        // with room for indices anyway, so only check indices into a table that's there
+        if data.save_version >= 9 && !header2.physical_materials.is_empty() {
+            check(
+                "physical_index",
+                brick.physical_index,
+                header2.physical_materials.len(),
+            )?;
+        }
+        if let BrickColor::Index(index) = brick.color {
+            check("color", index, header2.colors.len())?;
+        }
+        if brick.owner_index != 0 && header2.owner(brick.owner_index).is_none() {
+            return Err(ValidationError::IndexOutOfRange {
+                brick: i,
+                field: "owner_index",
+                index: brick.owner_index,
+                len: header2.brick_owners.len(),
+            });
+        }
+
+        // check components in name order, so the same save always reports the same error
+        let mut components = brick.components.iter().collect::<Vec<_>>();
+        components.sort_by_key(|&(name, _)| name);
+        for (name, values) in components {
+            let component =
+                data.components
+                    .get(name)
+                    .ok_or_else(|| ValidationError::UnknownComponent {
+                        brick: i,
+                        component: name.clone(),
+                    })?;
+
+            for (property, expected) in component.properties.iter() {
+                let value =
+                    values
+                        .get(property)
+                        .ok_or_else(|| ValidationError::MissingProperty {
+                            brick: i,
+                            component: name.clone(),
+                            property: property.clone(),
+                        })?;
+                check_value(i, name, property, expected, value)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that a component property's value has the type the component describes, and
+/// that any floats in it are finite.
+fn check_value(
+    brick: usize,
+    component: &str,
+    property: &str,
+    expected: &str,
+    value: &UnrealValue,
+) -> Result<(), ValidationError> {
+    // "Object" properties are read as classes
+    let found = value.type_name();
+    if found != expected && !(expected == "Object" && found == "Class") {
+        return Err(ValidationError::PropertyTypeMismatch {
+            brick,
+            component: component.to_owned(),
+            property: property.to_owned(),
+            expected: expected.to_owned(),
+            found,
+        });
+    }
+
+    let finite = match *value {
+        UnrealValue::Float(f) => f.is_finite(),
+        UnrealValue::Rotator(x, y, z) => x.is_finite() && y.is_finite() && z.is_finite(),
+        _ => true,
+    };
+    if !finite {
+        return Err(ValidationError::NonFiniteFloat {
+            brick,
+            component: component.to_owned(),
+            property: property.to_owned(),
+        });
+    }
+
+    Ok(())
+}
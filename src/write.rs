@@ -1,17 +1,28 @@
 use std::{
+    borrow::Borrow,
     cmp,
-    collections::{hash_map::Entry, HashMap},
-    io::{self, Write},
+    collections::{btree_map::Entry, BTreeMap},
+    convert::TryFrom,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
 };
 
-use bitstream_io::{BitWrite, BitWriter};
+use bitstream_io::{BitRecorder, BitWrite, BitWriter};
 use byteorder::{LittleEndian, WriteBytesExt};
-use flate2::{write::ZlibEncoder, Compression};
+use flate2::write::ZlibEncoder;
 use thiserror::Error;
 
+pub use crate::compression::Compression;
+
+#[cfg(feature = "tokio")]
+use tokio::io::AsyncWrite;
+
 use crate::{
+    compression,
     ext::write::*,
-    save::{BrickColor, SaveData, Size, UnrealType},
+    save::{Brick, BrickColor, Collision, Component, SaveData, Size},
+    validate::ValidationError,
     MAGIC_BYTES, SAVE_VERSION,
 };
 
@@ -20,293 +31,899 @@ use crate::{
 pub enum WriteError {
     #[error("generic io error: {0}")]
     IoError(#[from] io::Error),
-    #[error("brick is missing a component property")]
-    ComponentBrickError,
+    #[error("brick {brick} is missing property {property:?} of component {component:?}")]
+    MissingProperty {
+        brick: u32,
+        component: String,
+        property: String,
+    },
     #[error("brick specifies a component that is not described in the save data")]
     BrickComponentMismatch,
+    #[error("invalid save data: {0}")]
+    Invalid(#[from] ValidationError),
+    #[error("cannot write save version {0}")]
+    UnsupportedVersion(u16),
+    #[error("save version {version} cannot represent {what}")]
+    Unrepresentable { version: u16, what: &'static str },
+    #[error("expected {expected} bricks, but {found} were given")]
+    BrickCountMismatch { expected: usize, found: usize },
+    #[error("{section} is too large to write: {len} is more than a save can store")]
+    TooLarge { section: &'static str, len: usize },
+}
+
+/// Convert the length of `section` to the `i32` it is written as, failing if it doesn't fit.
+fn check_len(section: &'static str, len: usize) -> Result<i32, WriteError> {
+    i32::try_from(len).map_err(|_| WriteError::TooLarge { section, len })
+}
+
+/// The oldest save version a `SaveWriter` can write, see `SaveWriter::with_version`.
+pub const MIN_SAVE_VERSION: u16 = 8;
+
+/// A stage of writing a save, reported to the callback set with `SaveWriter::on_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteStage {
+    Header1,
+    Header2,
+    Preview,
+    Bricks,
+    Components,
+}
+
+/// A callback set with `SaveWriter::on_progress`.
+type Progress<'p> = dyn FnMut(WriteStage, usize, usize) + Send + 'p;
+
+/// How many bricks are encoded between each report of `WriteStage::Bricks`.
+const PROGRESS_INTERVAL: usize = 4096;
+
+/// The bricks that have a component, and their values for its properties, as they are
+/// encoded.
+struct ComponentBricks<'a> {
+    component: &'a Component,
+    properties: Vec<(&'a String, &'a String)>,
+    indices: Vec<u32>,
+    values: BitRecorder<u64, bitstream_io::LittleEndian>,
+}
+
+/// A zlib compressor, used to compress the sections of a save.
+///
+/// Implementors only need to produce a zlib stream from the bytes they are given.
+/// The section framing, including storing a section uncompressed when compressing
+/// it does not make it smaller, is handled by the writer. Compressors are shared between
+/// threads when sections are compressed in parallel (the `rayon` feature).
+pub trait Compressor: Send + Sync {
+    /// Compress `bytes` into a zlib stream.
+    fn compress(&self, bytes: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// The default `Compressor`, backed by `flate2`, compressing at the given level.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlateCompressor(pub Compression);
+
+impl Compressor for FlateCompressor {
+    fn compress(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(vec![], self.0);
+        encoder.write_all(bytes)?;
+        encoder.finish()
+    }
 }
 
 /// A save writer, which writes its `data` to its `writer` (a `Write`).
-pub struct SaveWriter<W: Write> {
+///
+/// The data can either be owned (a `SaveData`) or borrowed (a `&SaveData`).
+pub struct SaveWriter<'p, W: Write, D: Borrow<SaveData> = SaveData> {
     writer: W,
-    data: SaveData,
+    data: D,
     compressed: bool,
+    compressor: Box<dyn Compressor>,
+    version: u16,
+    progress: Option<Box<Progress<'p>>>,
 }
 
-impl<W: Write> SaveWriter<W> {
-    pub fn new(writer: W, data: SaveData) -> SaveWriter<W> {
+impl<'p, W: Write, D: Borrow<SaveData>> SaveWriter<'p, W, D> {
+    pub fn new(writer: W, data: D) -> SaveWriter<'p, W, D> {
         SaveWriter {
             writer,
             data,
             compressed: true,
+            compressor: Box::new(FlateCompressor::default()),
+            version: SAVE_VERSION,
+            progress: None,
         }
     }
 
-    pub fn uncompressed(writer: W, data: SaveData) -> SaveWriter<W> {
+    pub fn uncompressed(writer: W, data: D) -> SaveWriter<'p, W, D> {
         SaveWriter {
             writer,
             data,
             compressed: false,
+            compressor: Box::new(FlateCompressor::default()),
+            version: SAVE_VERSION,
+            progress: None,
         }
     }
 
-    pub fn write(mut self) -> Result<(), WriteError> {
-        // write header 0
-        {
-            self.writer.write_all(&MAGIC_BYTES)?;
-            self.writer.write_u16::<LittleEndian>(SAVE_VERSION)?;
-            self.writer
-                .write_i32::<LittleEndian>(self.data.game_version)?;
+    /// Create a save writer that compresses its sections at `level`.
+    ///
+    /// `Compression::none()` stores every section uncompressed, like `uncompressed`.
+    pub fn with_compression(writer: W, data: D, level: Compression) -> SaveWriter<'p, W, D> {
+        SaveWriter {
+            writer,
+            data,
+            compressed: level != Compression::none(),
+            compressor: Box::new(FlateCompressor(level)),
+            version: SAVE_VERSION,
+            progress: None,
+        }
+    }
+
+    /// Create a save writer that compresses its sections with `compressor`.
+    pub fn with_compressor(
+        writer: W,
+        data: D,
+        compressor: impl Compressor + 'static,
+    ) -> SaveWriter<'p, W, D> {
+        SaveWriter {
+            writer,
+            data,
+            compressed: true,
+            compressor: Box::new(compressor),
+            version: SAVE_VERSION,
+            progress: None,
         }
+    }
+
+    /// Set whether compression is disabled. When it is, sections are always stored
+    /// uncompressed without attempting to compress them, as with `uncompressed`.
+    pub fn set_compression_disabled(&mut self, disabled: bool) {
+        self.compressed = !disabled;
+    }
+
+    /// Write an older save `version` instead of the current one, for tools that don't
+    /// read the latest format. Versions from `MIN_SAVE_VERSION` up are supported.
+    ///
+    /// Data the older version has no place for is dropped: version 8 has no physical
+    /// materials or material intensities. Bricks with differing collision flags can't be
+    /// written before version 10, and fail with `WriteError::Unrepresentable`.
+    pub fn with_version(mut self, version: u16) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Call `progress` with the stage, and how much of it is done out of its total, as the
+    /// save is written.
+    ///
+    /// Each stage is reported once it is done. Bricks are also reported every few thousand
+    /// bricks as they are encoded, counting bricks, and components count the components
+    /// written. The headers and preview are counted as 1 of 1. With the `rayon` feature,
+    /// sections are compressed and written after every one is encoded, so the stages only
+    /// track encoding.
+    pub fn on_progress(
+        mut self,
+        progress: impl FnMut(WriteStage, usize, usize) + Send + 'p,
+    ) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Validate the save (see `SaveData::validate`), then write it if it is valid.
+    pub fn write_validated(self) -> Result<(), WriteError> {
+        self.data.borrow().validate()?;
+        self.write()
+    }
 
-        let brick_count = self.data.bricks.len();
-        let asset_name_count = cmp::max(self.data.header2.brick_assets.len(), 2);
-        let material_count = cmp::max(self.data.header2.materials.len(), 2);
-        let physical_material_count = cmp::max(self.data.header2.physical_materials.len(), 2);
-        let color_count = cmp::max(self.data.header2.colors.len(), 2);
+    /// Encode the save's compressible sections without compressing or writing them, e.g. to
+    /// compare the bytes against another save's when debugging.
+    pub fn write_sections(&self) -> Result<SaveSections, WriteError> {
+        let data = self.data.borrow();
+        let mut sections = SaveSections::default();
+        encode(
+            data,
+            &data.bricks,
+            data.bricks.len(),
+            self.version,
+            &mut |section, bytes| {
+                match section {
+                    Section::Header1 => sections.header1 = bytes,
+                    Section::Header2 => sections.header2 = bytes,
+                    Section::Bricks => sections.bricks = bytes,
+                    Section::Components => sections.components = bytes,
+                    Section::Header0 | Section::Preview => (),
+                }
+                Ok(())
+            },
+            &mut |_, _, _| (),
+        )?;
+        Ok(sections)
+    }
 
-        // write header 1
+    pub fn write(self) -> Result<(), WriteError> {
+        self.write_with(|data, version, emit, progress| {
+            encode(
+                data,
+                &data.bricks,
+                data.bricks.len(),
+                version,
+                emit,
+                progress,
+            )
+        })
+    }
+
+    /// Write the save with the bricks yielded by `bricks` instead of the data's own
+    /// `bricks`, which are ignored. Bricks are encoded as they are yielded, so a large save
+    /// can be generated without holding all of its bricks in memory.
+    ///
+    /// `brick_count` must be the number of bricks `bricks` yields, as it is written before
+    /// them. If it isn't, this fails with `WriteError::BrickCountMismatch`, possibly after
+    /// part of the save has been written. Bricks past `brick_count` are counted for the error,
+    /// but not encoded.
+    pub fn write_bricks_from<B, I>(self, brick_count: usize, bricks: I) -> Result<(), WriteError>
+    where
+        B: Borrow<Brick>,
+        I: IntoIterator<Item = B>,
+    {
+        self.write_with(|data, version, emit, progress| {
+            encode(data, bricks, brick_count, version, emit, progress)
+        })
+    }
+
+    /// Write the sections produced by `encode`, compressing them as configured.
+    fn write_with(
+        mut self,
+        encode: impl FnOnce(
+            &SaveData,
+            u16,
+            &mut dyn FnMut(Section, Vec<u8>) -> io::Result<()>,
+            &mut dyn FnMut(WriteStage, usize, usize),
+        ) -> Result<(), WriteError>,
+    ) -> Result<(), WriteError> {
+        let data = self.data.borrow();
+        let mut ignore = |_, _, _| ();
+        let progress: &mut dyn FnMut(WriteStage, usize, usize) = match &mut self.progress {
+            Some(progress) => progress,
+            None => &mut ignore,
+        };
+        let compressor = if self.compressed {
+            Some(&*self.compressor)
+        } else {
+            None
+        };
+
+        #[cfg(not(feature = "rayon"))]
         {
-            // this Vec<u8> will store the bytes to the header, and eventually
-            // will be compressed when necessary
-            let mut w: Vec<u8> = vec![];
-            w.write_string(self.data.header1.map)?;
-            w.write_string(self.data.header1.author.name.to_owned())?;
-            w.write_string(self.data.header1.description)?;
-            w.write_uuid(self.data.header1.author.id)?;
-
-            // if the host is None, then we assume it to be the
-            // same as the author. can safely write the same value
-            let host = self.data.header1.host.unwrap_or(self.data.header1.author);
-            w.write_string(host.name)?;
-            w.write_uuid(host.id)?;
-
-            w.write_all(&self.data.header1.save_time)?;
-            w.write_i32::<LittleEndian>(self.data.bricks.len() as i32)?;
-
-            write_compressed(&mut self.writer, w, self.compressed)?;
+            let writer = &mut self.writer;
+            encode(
+                data,
+                self.version,
+                &mut |section, bytes| writer.write_all(&frame(section, bytes, compressor)?),
+                progress,
+            )?;
         }
 
-        // write header 2
+        // build every section first, then compress them in parallel
+        #[cfg(feature = "rayon")]
         {
-            // see above for compression methods
-            let mut w: Vec<u8> = vec![];
+            use rayon::prelude::*;
+
+            let mut sections = vec![];
+            encode(
+                data,
+                self.version,
+                &mut |section, bytes| {
+                    sections.push((section, bytes));
+                    Ok(())
+                },
+                progress,
+            )?;
 
-            w.write_array(self.data.header2.mods, |writer, string| {
-                writer.write_string(string)
-            })?;
+            let sections = sections
+                .into_par_iter()
+                .map(|(section, bytes)| frame(section, bytes, compressor))
+                .collect::<io::Result<Vec<_>>>()?;
+            for bytes in sections {
+                self.writer.write_all(&bytes)?;
+            }
+        }
 
-            w.write_array(self.data.header2.brick_assets, |writer, string| {
-                writer.write_string(string)
-            })?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
 
-            w.write_array(self.data.header2.colors, |writer, color| {
-                writer.write_color_bgra(color)
-            })?;
+impl<'p, D: Borrow<SaveData>> SaveWriter<'p, BufWriter<File>, D> {
+    /// Create a save writer that writes to the file at `path` through a `BufWriter`,
+    /// creating the file or truncating it if it exists.
+    pub fn create<P: AsRef<Path>>(path: P, data: D) -> Result<Self, WriteError> {
+        Ok(SaveWriter::new(BufWriter::new(File::create(path)?), data))
+    }
+}
 
-            w.write_array(self.data.header2.materials, |writer, string| {
-                writer.write_string(string)
-            })?;
+/// An async save writer, which writes its `data` to its `writer` (a tokio `AsyncWrite`).
+///
+/// Sections are encoded and compressed in memory as `SaveWriter` does, and only writing
+/// them out is asynchronous.
+#[cfg(feature = "tokio")]
+pub struct AsyncSaveWriter<'p, W: AsyncWrite + Unpin, D: Borrow<SaveData> = SaveData> {
+    writer: W,
+    data: D,
+    compressed: bool,
+    compressor: Box<dyn Compressor>,
+    version: u16,
+    progress: Option<Box<Progress<'p>>>,
+}
+
+#[cfg(feature = "tokio")]
+impl<'p, W: AsyncWrite + Unpin, D: Borrow<SaveData>> AsyncSaveWriter<'p, W, D> {
+    pub fn new(writer: W, data: D) -> AsyncSaveWriter<'p, W, D> {
+        AsyncSaveWriter {
+            writer,
+            data,
+            compressed: true,
+            compressor: Box::new(FlateCompressor::default()),
+            version: SAVE_VERSION,
+            progress: None,
+        }
+    }
+
+    pub fn uncompressed(writer: W, data: D) -> AsyncSaveWriter<'p, W, D> {
+        AsyncSaveWriter {
+            writer,
+            data,
+            compressed: false,
+            compressor: Box::new(FlateCompressor::default()),
+            version: SAVE_VERSION,
+            progress: None,
+        }
+    }
+
+    /// Create a save writer that compresses its sections at `level`.
+    ///
+    /// `Compression::none()` stores every section uncompressed, like `uncompressed`.
+    pub fn with_compression(writer: W, data: D, level: Compression) -> AsyncSaveWriter<'p, W, D> {
+        AsyncSaveWriter {
+            writer,
+            data,
+            compressed: level != Compression::none(),
+            compressor: Box::new(FlateCompressor(level)),
+            version: SAVE_VERSION,
+            progress: None,
+        }
+    }
+
+    /// Create a save writer that compresses its sections with `compressor`.
+    pub fn with_compressor(
+        writer: W,
+        data: D,
+        compressor: impl Compressor + 'static,
+    ) -> AsyncSaveWriter<'p, W, D> {
+        AsyncSaveWriter {
+            writer,
+            data,
+            compressed: true,
+            compressor: Box::new(compressor),
+            version: SAVE_VERSION,
+            progress: None,
+        }
+    }
 
-            w.write_array(
-                self.data.header2.brick_owners,
-                |writer, brick_owner| -> io::Result<()> {
-                    writer.write_uuid(brick_owner.id)?;
-                    writer.write_string(brick_owner.name)?;
-                    writer.write_i32::<LittleEndian>(brick_owner.bricks as i32)?;
+    /// Set whether compression is disabled, see `SaveWriter::set_compression_disabled`.
+    pub fn set_compression_disabled(&mut self, disabled: bool) {
+        self.compressed = !disabled;
+    }
+
+    /// Write an older save `version` instead of the current one, see
+    /// `SaveWriter::with_version`.
+    pub fn with_version(mut self, version: u16) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Call `progress` as the save is written, see `SaveWriter::on_progress`.
+    ///
+    /// Sections are encoded and compressed before any of them are written, so the stages only
+    /// track encoding.
+    pub fn on_progress(
+        mut self,
+        progress: impl FnMut(WriteStage, usize, usize) + Send + 'p,
+    ) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Validate the save (see `SaveData::validate`), then write it if it is valid.
+    pub async fn write_validated(self) -> Result<(), WriteError> {
+        self.data.borrow().validate()?;
+        self.write().await
+    }
+
+    pub async fn write(mut self) -> Result<(), WriteError> {
+        use tokio::io::AsyncWriteExt;
+
+        // encode and compress every section up front, as that part isn't async
+        let sections = {
+            let compressor: Option<&dyn Compressor> = if self.compressed {
+                Some(&*self.compressor)
+            } else {
+                None
+            };
+
+            let data = self.data.borrow();
+            let mut ignore = |_, _, _| ();
+            let progress: &mut dyn FnMut(WriteStage, usize, usize) = match &mut self.progress {
+                Some(progress) => progress,
+                None => &mut ignore,
+            };
+            let mut sections = vec![];
+            encode(
+                data,
+                &data.bricks,
+                data.bricks.len(),
+                self.version,
+                &mut |section, bytes| {
+                    sections.push(frame(section, bytes, compressor)?);
                     Ok(())
                 },
+                progress,
             )?;
+            sections
+        };
+
+        for section in sections {
+            self.writer.write_all(&section).await?;
+        }
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
+
+/// The uncompressed bytes of each compressible section of a save, as they are before
+/// compression. Created by `SaveWriter::write_sections`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveSections {
+    pub header1: Vec<u8>,
+    pub header2: Vec<u8>,
+    pub bricks: Vec<u8>,
+    pub components: Vec<u8>,
+}
+
+/// The uncompressed size in bytes of each section of a save, see `SaveData::estimate_size`.
+///
+/// These are the sizes written before each compressed section, so they don't depend on
+/// compression. `header0` (magic and versions) and `preview` are never compressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeEstimate {
+    pub header0: usize,
+    pub header1: usize,
+    pub header2: usize,
+    pub preview: usize,
+    pub bricks: usize,
+    pub components: usize,
+}
+
+impl SizeEstimate {
+    /// The size of the whole file when written uncompressed, including the 8 bytes of
+    /// sizes before each compressed section. A compressed save is at most this size.
+    pub fn total(&self) -> usize {
+        self.header0
+            + self.header1
+            + self.header2
+            + self.preview
+            + self.bricks
+            + self.components
+            + 4 * 8
+    }
+}
+
+pub(crate) fn estimate_size(data: &SaveData, version: u16) -> Result<SizeEstimate, WriteError> {
+    let mut estimate = SizeEstimate::default();
+    encode(
+        data,
+        &data.bricks,
+        data.bricks.len(),
+        version,
+        &mut |section, bytes| {
+            let size = match section {
+                Section::Header0 => &mut estimate.header0,
+                Section::Header1 => &mut estimate.header1,
+                Section::Header2 => &mut estimate.header2,
+                Section::Preview => &mut estimate.preview,
+                Section::Bricks => &mut estimate.bricks,
+                Section::Components => &mut estimate.components,
+            };
+            *size = bytes.len();
+            Ok(())
+        },
+        &mut |_, _, _| (),
+    )?;
+    Ok(estimate)
+}
 
-            w.write_array(self.data.header2.physical_materials, |writer, string| {
+pub(crate) fn content_hash(data: &SaveData) -> Result<u64, WriteError> {
+    // 64-bit FNV-1a, which unlike std's hashers is guaranteed to never change
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut update = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+
+    encode(
+        data,
+        &data.bricks,
+        data.bricks.len(),
+        SAVE_VERSION,
+        &mut |section, bytes| {
+            if let Section::Bricks | Section::Components = section {
+                // prefix each section with its length, so bytes can't move between them
+                update(&(bytes.len() as u64).to_le_bytes());
+                update(&bytes);
+            }
+            Ok(())
+        },
+        &mut |_, _, _| (),
+    )?;
+    Ok(hash)
+}
+
+/// A section of a save file, in the order they are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Header0,
+    Header1,
+    Header2,
+    Preview,
+    Bricks,
+    Components,
+}
+
+impl Section {
+    /// The name of this section if it is framed with its size, for `WriteError::TooLarge`.
+    fn framed_name(self) -> Option<&'static str> {
+        match self {
+            Section::Header0 | Section::Preview => None,
+            Section::Header1 => Some("header 1"),
+            Section::Header2 => Some("header 2"),
+            Section::Bricks => Some("brick data"),
+            Section::Components => Some("component data"),
+        }
+    }
+}
+
+/// Encode `data` as save `version` with `bricks` (of which there are `brick_count`) in
+/// place of its own, passing each section's uncompressed bytes to `emit` in order, and
+/// reporting to `progress` as described by `SaveWriter::on_progress`.
+fn encode<B: Borrow<Brick>>(
+    data: &SaveData,
+    bricks: impl IntoIterator<Item = B>,
+    brick_count: usize,
+    version: u16,
+    emit: &mut dyn FnMut(Section, Vec<u8>) -> io::Result<()>,
+    progress: &mut dyn FnMut(WriteStage, usize, usize),
+) -> Result<(), WriteError> {
+    if !(MIN_SAVE_VERSION..=SAVE_VERSION).contains(&version) {
+        return Err(WriteError::UnsupportedVersion(version));
+    }
+
+    // compressed sections are framed with their size as an i32
+    let mut emit = |section: Section, bytes: Vec<u8>| -> Result<(), WriteError> {
+        if let Some(name) = section.framed_name() {
+            check_len(name, bytes.len())?;
+        }
+        Ok(emit(section, bytes)?)
+    };
+
+    // write header 0
+    {
+        let mut w: Vec<u8> = vec![];
+        w.write_all(&MAGIC_BYTES)?;
+        w.write_u16::<LittleEndian>(version)?;
+        w.write_i32::<LittleEndian>(data.game_version)?;
+        emit(Section::Header0, w)?;
+    }
+
+    let asset_name_count = cmp::max(data.header2.brick_assets.len(), 2);
+    let material_count = cmp::max(data.header2.materials.len(), 2);
+    let physical_material_count = cmp::max(data.header2.physical_materials.len(), 2);
+    let color_count = cmp::max(data.header2.colors.len(), 2);
+
+    // write header 1
+    {
+        // this Vec<u8> will store the bytes to the header, and eventually
+        // will be compressed when necessary
+        let mut w: Vec<u8> = vec![];
+        w.write_string(&data.header1.map)?;
+        w.write_string(&data.header1.author.name)?;
+        w.write_string(&data.header1.description)?;
+        w.write_uuid(data.header1.author.id)?;
+
+        // if the host is None, then we assume it to be the
+        // same as the author. can safely write the same value
+        let host = data.header1.host.as_ref().unwrap_or(&data.header1.author);
+        w.write_string(&host.name)?;
+        w.write_uuid(host.id)?;
+
+        w.write_all(&data.header1.save_time)?;
+        w.write_i32::<LittleEndian>(check_len("bricks", brick_count)?)?;
+        w.write_all(&data.header1.raw_extra)?;
+
+        emit(Section::Header1, w)?;
+        progress(WriteStage::Header1, 1, 1);
+    }
+
+    // write header 2
+    {
+        // see above for compression methods
+        let mut w: Vec<u8> = vec![];
+
+        let header2 = &data.header2;
+        for &(table, len) in &[
+            ("mods", header2.mods.len()),
+            ("brick assets", header2.brick_assets.len()),
+            ("colors", header2.colors.len()),
+            ("materials", header2.materials.len()),
+            ("brick owners", header2.brick_owners.len()),
+            ("physical materials", header2.physical_materials.len()),
+        ] {
+            check_len(table, len)?;
+        }
+        for brick_owner in header2.brick_owners.iter() {
+            check_len("brick owner bricks", brick_owner.bricks as usize)?;
+        }
+
+        w.write_array(&data.header2.mods, |writer, string| {
+            writer.write_string(string)
+        })?;
+
+        w.write_array(&data.header2.brick_assets, |writer, string| {
+            writer.write_string(string)
+        })?;
+
+        w.write_array(&data.header2.colors, |writer, color| {
+            writer.write_color_bgra(color)
+        })?;
+
+        w.write_array(&data.header2.materials, |writer, string| {
+            writer.write_string(string)
+        })?;
+
+        w.write_array(
+            &data.header2.brick_owners,
+            |writer, brick_owner| -> io::Result<()> {
+                writer.write_uuid(brick_owner.id)?;
+                writer.write_string(&brick_owner.name)?;
+                // checked to fit above
+                writer.write_i32::<LittleEndian>(brick_owner.bricks as i32)?;
+                Ok(())
+            },
+        )?;
+
+        // version >= 9: physical materials
+        if version >= 9 {
+            w.write_array(&data.header2.physical_materials, |writer, string| {
                 writer.write_string(string)
             })?;
+        }
 
-            write_compressed(&mut self.writer, w, self.compressed)?;
+        w.write_all(&data.header2.raw_extra)?;
+
+        emit(Section::Header2, w)?;
+        progress(WriteStage::Header2, 1, 1);
+    }
+
+    // write preview
+    {
+        let mut w: Vec<u8> = vec![];
+        w.write_u8(data.preview.type_byte())?;
+        if let Some(bytes) = data.preview.as_bytes() {
+            w.write_i32::<LittleEndian>(check_len("preview", bytes.len())?)?;
+            w.write_all(bytes)?;
         }
+        emit(Section::Preview, w)?;
+        progress(WriteStage::Preview, 1, 1);
+    }
 
-        // write preview
-        {
-            let preview_type = self.data.preview.type_byte();
-            self.writer.write_u8(preview_type)?;
-            match preview_type {
-                0 => (),
-                _ => {
-                    let bytes = self.data.preview.unwrap();
-                    self.writer.write_i32::<LittleEndian>(bytes.len() as i32)?;
-                    self.writer.write_all(&bytes)?
-                }
+    // write bricks and components
+    {
+        let mut vec = vec![];
+        let mut bits = BitWriter::endian(&mut vec, bitstream_io::LittleEndian);
+
+        // components are written sorted by name, so the output doesn't depend on the order
+        // of `data.components`
+        let mut component_bricks: BTreeMap<&str, ComponentBricks> = BTreeMap::new();
+
+        let mut found = 0;
+        let mut next_report = PROGRESS_INTERVAL;
+        let mut bricks = bricks.into_iter().enumerate();
+        while let Some((i, brick)) = bricks.next() {
+            if i == brick_count {
+                // don't encode any more bricks than were asked for, only count them
+                found = brick_count + 1 + bricks.count();
+                break;
             }
-        }
+            found = i + 1;
 
-        // write bricks and components
-        {
-            let mut vec = vec![];
-            let mut bits = BitWriter::endian(&mut vec, bitstream_io::LittleEndian);
-
-            let mut component_bricks: HashMap<String, Vec<(u32, HashMap<String, UnrealType>)>> =
-                HashMap::new();
-
-            for (i, brick) in self.data.bricks.into_iter().enumerate() {
-                bits.byte_align()?;
-
-                // write asset name index: <asset_name_index: u32; N>
-                bits.write_uint(brick.asset_name_index, asset_name_count as u32)?;
-
-                // write brick size:
-                // <procedural?: bit>[x: uint_packed][y: uint_packed][z: uint_packed]
-                match brick.size {
-                    Size::Procedural(x, y, z) => {
-                        bits.write_bit(true)?;
-                        bits.write_uint_packed(x)?;
-                        bits.write_uint_packed(y)?;
-                        bits.write_uint_packed(z)?;
-                    }
-                    Size::Empty => bits.write_bit(false)?,
-                }
+            if i == next_report {
+                progress(WriteStage::Bricks, i, brick_count);
+                next_report += PROGRESS_INTERVAL;
+            }
 
-                // write position:
-                // <x: int_packed><y: int_packed><z: int_packed>
-                bits.write_int_packed(brick.position.0)?;
-                bits.write_int_packed(brick.position.1)?;
-                bits.write_int_packed(brick.position.2)?;
+            let brick = brick.borrow();
+            bits.byte_align()?;
 
-                // write orientation: <orientation: uint; 24>
-                let orientation = ((brick.direction as u32) << 2) | (brick.rotation as u32);
-                bits.write_uint(orientation, 24)?;
+            // write asset name index: <asset_name_index: u32; N>
+            bits.write_uint(brick.asset_name_index, asset_name_count as u32)?;
+
+            // write brick size:
+            // <procedural?: bit>[x: uint_packed][y: uint_packed][z: uint_packed]
+            match brick.size {
+                Size::Procedural(x, y, z) => {
+                    bits.write_bit(true)?;
+                    bits.write_uint_packed(x)?;
+                    bits.write_uint_packed(y)?;
+                    bits.write_uint_packed(z)?;
+                }
+                Size::Empty => bits.write_bit(false)?,
+            }
 
-                // write collision bits:
-                // <player: bit><weapon: bit><interaction: bit><tool: bit>
-                bits.write_bit(brick.collision.player)?;
-                bits.write_bit(brick.collision.weapon)?;
-                bits.write_bit(brick.collision.interaction)?;
-                bits.write_bit(brick.collision.tool)?;
+            // write position:
+            // <x: int_packed><y: int_packed><z: int_packed>
+            bits.write_int_packed(brick.position.0)?;
+            bits.write_int_packed(brick.position.1)?;
+            bits.write_int_packed(brick.position.2)?;
+
+            // write orientation: <orientation: uint; 24>
+            bits.write_uint(brick.orientation().packed(), 24)?;
+
+            // write collision bits:
+            // version >= 10: <player: bit><weapon: bit><interaction: bit><tool: bit>
+            // otherwise: <collision: bit>
+            let collision = &brick.collision;
+            if version >= 10 {
+                bits.write_bit(collision.player)?;
+                bits.write_bit(collision.weapon)?;
+                bits.write_bit(collision.interaction)?;
+                bits.write_bit(collision.tool)?;
+            } else if *collision == Collision::for_all(collision.player) {
+                bits.write_bit(collision.player)?;
+            } else {
+                return Err(WriteError::Unrepresentable {
+                    version,
+                    what: "differing collision flags",
+                });
+            }
 
-                // write visibility: <visibility: bit>
-                bits.write_bit(brick.visibility)?;
+            // write visibility: <visibility: bit>
+            bits.write_bit(brick.visibility)?;
 
-                // write material index: <material_index: u32; N>
-                bits.write_uint(brick.material_index, material_count as u32)?;
+            // write material index: <material_index: u32; N>
+            bits.write_uint(brick.material_index, material_count as u32)?;
 
+            if version >= 9 {
                 // write physical index: <physical_index: u32; N>
                 bits.write_uint(brick.physical_index, physical_material_count as u32)?;
 
                 // write material intensity: <material_intensity: u32; 11>
                 bits.write_uint(brick.material_intensity, 11)?;
+            }
 
-                // write color:
-                // <unique?: bit 0><index: uint; N> OR
-                // <unique?: bit 1><r: byte><g: byte><b: byte>
-                match brick.color {
-                    BrickColor::Index(ind) => {
-                        bits.write_bit(false)?;
-                        bits.write_uint(ind, color_count as u32)?;
-                    }
-                    BrickColor::Unique(color) => {
-                        bits.write_bit(true)?;
-                        bits.write_bytes(&[color.r, color.g, color.b])?;
-                    }
+            // write color:
+            // <unique?: bit 0><index: uint; N> OR
+            // version >= 9: <unique?: bit 1><r: byte><g: byte><b: byte>
+            // otherwise: <unique?: bit 1><b: byte><g: byte><r: byte><a: byte>
+            match &brick.color {
+                BrickColor::Index(ind) => {
+                    bits.write_bit(false)?;
+                    bits.write_uint(*ind, color_count as u32)?;
                 }
-
-                // write owner index: <owner_index: uint packed>
-                bits.write_uint_packed(brick.owner_index)?;
-
-                for (key, props) in brick.components.into_iter() {
-                    let entry = (i as u32, props);
-
-                    match component_bricks.entry(key) {
-                        Entry::Occupied(mut v) => {
-                            v.get_mut().push(entry);
-                        }
-                        Entry::Vacant(v) => {
-                            v.insert(vec![entry]);
-                        }
-                    }
+                BrickColor::Unique(color) if version >= 9 => {
+                    bits.write_bit(true)?;
+                    bits.write_bytes(&color.to_rgb_bytes())?;
+                }
+                BrickColor::Unique(color) => {
+                    bits.write_bit(true)?;
+                    bits.write_bytes(&color.to_bgra_bytes())?;
                 }
             }
 
-            bits.byte_align()?;
-
-            write_compressed(&mut self.writer, vec, self.compressed)?;
-
-            let mut vec: Vec<u8> = vec![];
-            vec.write_i32::<LittleEndian>(component_bricks.len() as i32)?;
-
-            for (name, brick_list) in component_bricks.into_iter() {
-                let component = match self.data.components.remove(&name) {
-                    Some(c) => c,
+            // write owner index: <owner_index: uint packed>
+            bits.write_uint_packed(brick.owner_index)?;
+
+            // encode the brick's component values now, so the brick isn't needed later
+            for (key, props) in brick.components.iter() {
+                let bricks = match data.components.get_key_value(key) {
+                    Some((name, component)) => match component_bricks.entry(name.as_str()) {
+                        Entry::Occupied(v) => v.into_mut(),
+                        Entry::Vacant(v) => v.insert(ComponentBricks {
+                            component,
+                            properties: component.properties.iter().collect(),
+                            indices: vec![],
+                            values: BitRecorder::new(),
+                        }),
+                    },
                     None => return Err(WriteError::BrickComponentMismatch),
                 };
 
-                vec.write_string(name.to_owned())?;
+                bricks.indices.push(i as u32);
+                for (p, _) in bricks.properties.iter() {
+                    let value = props.get(*p).ok_or_else(|| WriteError::MissingProperty {
+                        brick: i as u32,
+                        component: key.clone(),
+                        property: (*p).clone(),
+                    })?;
+                    bricks.values.write_unreal(value)?;
+                }
+            }
+        }
 
-                let mut bits = BitWriter::endian(Vec::new(), bitstream_io::LittleEndian);
+        if found != brick_count {
+            return Err(WriteError::BrickCountMismatch {
+                expected: brick_count,
+                found,
+            });
+        }
 
-                // write version
-                bits.write_i32(component.version)?;
+        bits.byte_align()?;
 
-                // write brick indices
-                bits.write_array(&brick_list, |writer, (i, _)| {
-                    writer.write_uint(*i, cmp::max(brick_count as u32, 2))
-                })?;
+        emit(Section::Bricks, vec)?;
+        progress(WriteStage::Bricks, brick_count, brick_count);
 
-                // write properties
-                let properties = component.properties.into_iter().collect::<Vec<_>>();
+        let mut vec: Vec<u8> = vec![];
+        let component_count = component_bricks.len();
+        vec.write_i32::<LittleEndian>(check_len("components", component_count)?)?;
 
-                bits.write_array(&properties, |writer, (key, val)| -> io::Result<()> {
-                    writer.write_string(key.clone())?;
-                    writer.write_string(val.clone())?;
-                    Ok(())
-                })?;
-
-                // read brick indices
-                // only continue if the component had some bricks
-                for (_, mut props) in brick_list.into_iter() {
-                    for (p, _) in properties.iter() {
-                        bits.write_unreal(props.remove(p).ok_or(WriteError::ComponentBrickError)?)?;
-                    }
-                }
+        for (name, bricks) in component_bricks.into_iter() {
+            vec.write_string(name)?;
 
-                bits.byte_align()?;
+            let mut bits = BitWriter::endian(Vec::new(), bitstream_io::LittleEndian);
 
-                let bit_vec = bits.into_writer();
-                vec.write_i32::<LittleEndian>(bit_vec.len() as i32)?;
-                vec.extend(bit_vec.into_iter());
-            }
+            // write version
+            bits.write_i32(bricks.component.version)?;
 
-            write_compressed(&mut self.writer, vec, self.compressed)?;
-        }
+            // write brick indices. the width is at least 2 like the other indices, and an
+            // empty list is just its length
+            bits.write_array(&bricks.indices, |writer, i| {
+                writer.write_uint(*i, cmp::max(brick_count as u32, 2))
+            })?;
 
-        Ok(())
-    }
-}
+            // write properties
+            bits.write_array(&bricks.properties, |writer, (key, val)| -> io::Result<()> {
+                writer.write_string(key)?;
+                writer.write_string(val)?;
+                Ok(())
+            })?;
 
-/// Write a `Vec<u8>` out to a `Write`, following the BRS spec for compression.
-fn write_compressed(
-    writer: &mut impl Write,
-    vec: Vec<u8>,
-    should_compress: bool,
-) -> io::Result<()> {
-    if !should_compress {
-        writer.write_i32::<LittleEndian>(vec.len() as i32)?;
-        writer.write_i32::<LittleEndian>(0)?;
-        writer.write_all(&vec[..])?;
-        return Ok(());
-    }
+            // write each brick's values, in the order of the properties
+            bricks.values.playback(&mut bits)?;
 
-    let compressed = ZlibEncoder::new(vec.clone(), Compression::default()).finish()?;
+            bits.byte_align()?;
 
-    writer.write_i32::<LittleEndian>(vec.len() as i32)?;
+            let bit_vec = bits.into_writer();
+            vec.write_i32::<LittleEndian>(check_len("component data", bit_vec.len())?)?;
+            vec.extend(bit_vec);
+        }
 
-    if compressed.len() < vec.len() {
-        // compressed is smaller, write (unc_size: i32, c_size: i32, bytes)
-        writer.write_i32::<LittleEndian>(compressed.len() as i32)?;
-        writer.write_all(&compressed[..])?;
-    } else {
-        // write uncompressed (unc_size: i32, c_size: i32 = 0, bytes)
-        writer.write_i32::<LittleEndian>(0)?;
-        writer.write_all(&vec[..])?;
+        emit(Section::Components, vec)?;
+        progress(WriteStage::Components, component_count, component_count);
     }
 
     Ok(())
 }
+
+/// Get the bytes of `section` as they appear in the file. Header 0 and the preview are
+/// written as-is, and every other section is framed and compressed by
+/// `compression::write_section`.
+fn frame(
+    section: Section,
+    bytes: Vec<u8>,
+    compressor: Option<&dyn Compressor>,
+) -> io::Result<Vec<u8>> {
+    match section {
+        Section::Header0 | Section::Preview => Ok(bytes),
+        _ => {
+            let mut framed = vec![];
+            compression::write_section(&mut framed, &bytes, compressor)?;
+            Ok(framed)
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use brickadia::{
+    builder::SaveBuilder,
+    save::{Brick, BrickColor, Color, User},
+};
+use uuid::Uuid;
+
+#[test]
+fn interns_tables() {
+    let red = Color::from_rgba(255, 0, 0, 255);
+    let blue = Color::from_rgba(0, 0, 255, 255);
+    let user = User {
+        name: "Builder".into(),
+        id: Uuid::from_u128(7),
+    };
+
+    let mut builder = SaveBuilder::default();
+    let owner = builder.owner_index(&user);
+    assert_eq!(owner, 1);
+    for (i, color) in [&red, &blue, &red].iter().enumerate() {
+        let brick = Brick {
+            position: (i as i32 * 10, 0, 6),
+            owner_index: owner,
+            ..Default::default()
+        };
+        builder.add_brick_named(
+            "PB_DefaultBrick",
+            "BMC_Plastic",
+            "BPMC_Default",
+            color,
+            brick,
+        );
+    }
+    builder.add_brick_named(
+        "PB_DefaultTile",
+        "BMC_Glow",
+        "BPMC_Default",
+        &blue,
+        Brick::default(),
+    );
+    assert_eq!(builder.owner_index(&user), owner);
+
+    let save = builder.build();
+    let header2 = &save.header2;
+    assert_eq!(header2.brick_assets, ["PB_DefaultBrick", "PB_DefaultTile"]);
+    assert_eq!(header2.materials, ["BMC_Plastic", "BMC_Glow"]);
+    assert_eq!(header2.physical_materials, ["BPMC_Default"]);
+    assert_eq!(header2.colors, [red, blue]);
+    assert_eq!(header2.brick_owners.len(), 1);
+    assert_eq!(header2.brick_owners[0].bricks, 3);
+
+    assert_eq!(save.header1.brick_count, 4);
+    let indices = save
+        .bricks
+        .iter()
+        .map(|b| (b.asset_name_index, b.material_index, b.color.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        indices,
+        [
+            (0, 0, BrickColor::Index(0)),
+            (0, 0, BrickColor::Index(1)),
+            (0, 0, BrickColor::Index(0)),
+            (1, 1, BrickColor::Index(1)),
+        ]
+    );
+}
+
+#[test]
+fn default_matches_new() {
+    // the tables start out empty, so interning never duplicates an entry
+    let save = SaveBuilder::default().build();
+    assert!(save.header2.brick_assets.is_empty());
+    assert!(save.header2.materials.is_empty());
+    assert!(save.header2.physical_materials.is_empty());
+
+    let mut builder = SaveBuilder::default();
+    assert_eq!(builder.asset_index("PB_DefaultBrick"), 0);
+    assert_eq!(builder.material_index("BMC_Plastic"), 0);
+    assert_eq!(builder.physical_material_index("BPMC_Default"), 0);
+    let save = builder.build();
+    assert_eq!(save.header2.brick_assets, ["PB_DefaultBrick"]);
+    assert_eq!(save.header2.materials, ["BMC_Plastic"]);
+}
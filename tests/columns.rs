@@ -0,0 +1,74 @@
+use brickadia::save::{
+    Brick, BrickColor, BrickColumns, Collision, Color, Direction, Orientation, Rotation, SaveData,
+    Size,
+};
+
+fn bricks() -> Vec<Brick> {
+    (0..10u32)
+        .map(|i| {
+            let mut brick = Brick {
+                asset_name_index: i % 3,
+                size: if i % 2 == 0 {
+                    Size::Procedural(5, 5, i + 1)
+                } else {
+                    Size::Empty
+                },
+                position: (i as i32 * 10, -(i as i32), 6),
+                collision: Collision::for_all(i % 4 != 0),
+                visibility: i != 3,
+                material_index: i % 2,
+                material_intensity: i,
+                color: if i % 3 == 0 {
+                    BrickColor::Unique(Color::from_rgba(i as u8, 0, 0, 255))
+                } else {
+                    BrickColor::Index(i)
+                },
+                owner_index: i % 2,
+                ..Default::default()
+            };
+            brick.set_orientation(Orientation::new(Direction::XNegative, Rotation::Deg180));
+            if i == 5 {
+                brick.set_component_property("BCD_PointLight", "Brightness", 50.0f32);
+            }
+            brick
+        })
+        .collect()
+}
+
+#[test]
+fn round_trip() {
+    let save = SaveData {
+        bricks: bricks(),
+        ..Default::default()
+    };
+
+    let columns = save.to_columns();
+    assert_eq!(columns.len(), 10);
+    assert!(!columns.is_empty());
+    assert_eq!(columns.positions[2], (20, -2, 6));
+    assert_eq!(columns.material_intensities[7], 7);
+    assert_eq!(
+        columns.orientations[0],
+        Orientation::new(Direction::XNegative, Rotation::Deg180)
+    );
+    assert!(columns.components[5].contains_key("BCD_PointLight"));
+
+    let joined = SaveData::from_columns(columns);
+    assert_eq!(joined.bricks, save.bricks);
+    assert_eq!(joined.header1.brick_count, 10);
+}
+
+#[test]
+fn empty_columns() {
+    let columns = BrickColumns::default();
+    assert!(columns.is_empty());
+    assert!(SaveData::from_columns(columns).bricks.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "brick columns have different lengths")]
+fn mismatched_columns() {
+    let mut columns = BrickColumns::from_bricks(&bricks());
+    columns.colors.pop();
+    columns.into_bricks();
+}
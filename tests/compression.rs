@@ -0,0 +1,78 @@
+use brickadia::{
+    compression::{read_compressed, write_compressed, Compression},
+    read::ReadError,
+};
+
+#[test]
+fn round_trip() {
+    let bytes = b"brick ".repeat(200);
+    for &level in &[
+        Compression::none(),
+        Compression::fast(),
+        Compression::best(),
+    ] {
+        let mut section = vec![];
+        write_compressed(&mut section, &bytes, level).unwrap();
+        assert_eq!(&section[0..4], &(bytes.len() as i32).to_le_bytes());
+        if level == Compression::none() {
+            assert_eq!(&section[4..8], &[0; 4]);
+            assert_eq!(section.len(), bytes.len() + 8);
+        } else {
+            assert!(section.len() < bytes.len());
+        }
+
+        let mut reader = &section[..];
+        assert_eq!(read_compressed(&mut reader).unwrap(), bytes);
+        assert!(reader.is_empty());
+    }
+
+    // bytes that don't compress are stored as they are
+    let mut section = vec![];
+    write_compressed(&mut section, &[1, 2, 3], Compression::best()).unwrap();
+    assert_eq!(section, [3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3]);
+
+    let mut section = vec![];
+    write_compressed(&mut section, &[], Compression::default()).unwrap();
+    assert_eq!(
+        read_compressed(&mut &section[..]).unwrap(),
+        Vec::<u8>::new()
+    );
+}
+
+#[test]
+fn corrupt_sections() {
+    let bytes = b"brick ".repeat(200);
+    let mut section = vec![];
+    write_compressed(&mut section, &bytes, Compression::default()).unwrap();
+
+    // truncated compressed data
+    let truncated = &section[..section.len() - 10];
+    assert!(matches!(
+        read_compressed(&mut &truncated[..]),
+        Err(ReadError::CorruptSection { .. })
+    ));
+
+    // a compressed size no smaller than the uncompressed size
+    let mut invalid = section.clone();
+    invalid[4..8].copy_from_slice(&(bytes.len() as i32).to_le_bytes());
+    assert!(matches!(
+        read_compressed(&mut &invalid[..]),
+        Err(ReadError::InvalidCompression)
+    ));
+
+    // declaring more bytes than the data decompresses to
+    let mut short = section.clone();
+    short[0..4].copy_from_slice(&(bytes.len() as i32 + 1).to_le_bytes());
+    assert!(matches!(
+        read_compressed(&mut &short[..]),
+        Err(ReadError::CorruptSection { got, .. }) if got == bytes.len()
+    ));
+
+    // declaring fewer bytes than the data decompresses to
+    let mut long = section.clone();
+    long[0..4].copy_from_slice(&(bytes.len() as i32 - 1).to_le_bytes());
+    assert!(matches!(
+        read_compressed(&mut &long[..]),
+        Err(ReadError::SizeMismatch)
+    ));
+}
@@ -0,0 +1,82 @@
+use brickadia::save::{BrickAsset, Header2, Material, User};
+use uuid::Uuid;
+
+#[test]
+fn default_materials() {
+    let header2 = Header2::default().with_default_materials();
+    assert_eq!(
+        header2.materials,
+        [
+            "BMC_Plastic",
+            "BMC_Glow",
+            "BMC_Glass",
+            "BMC_Metallic",
+            "BMC_Hologram"
+        ]
+    );
+}
+
+#[test]
+fn owners() {
+    let mut header2 = Header2 {
+        brick_owners: vec![
+            User {
+                name: "First".into(),
+                id: Uuid::from_u128(1),
+            }
+            .into(),
+            User {
+                name: "Second".into(),
+                id: Uuid::from_u128(2),
+            }
+            .into(),
+        ],
+        ..Default::default()
+    };
+
+    // owner indices are 1-indexed, with 0 being public
+    assert!(header2.owner(0).is_none());
+    assert_eq!(header2.owner(1).unwrap().name, "First");
+    assert_eq!(header2.owner(2).unwrap().name, "Second");
+    assert!(header2.owner(3).is_none());
+
+    header2.owner_mut(2).unwrap().bricks = 5;
+    assert_eq!(header2.brick_owners[1].bricks, 5);
+}
+
+#[test]
+fn materials_and_assets() {
+    assert_eq!(Material::from("BMC_Glow"), Material::Glow);
+    assert_eq!(Material::GhostFail.as_str(), "BMC_Ghost_Fail");
+    assert_eq!(
+        Material::from("BMC_Modded"),
+        Material::Custom("BMC_Modded".into())
+    );
+    assert_eq!(
+        String::from(Material::Custom("BMC_Modded".into())),
+        "BMC_Modded"
+    );
+    assert_eq!(BrickAsset::from("PB_DefaultTile"), BrickAsset::Tile);
+    assert_eq!(BrickAsset::RampCrest.to_string(), "PB_DefaultRampCrest");
+    assert_eq!(
+        "B_1x1_Round".parse::<BrickAsset>().unwrap(),
+        BrickAsset::Custom("B_1x1_Round".into())
+    );
+
+    let mut header2 = Header2::default().with_default_materials();
+    assert_eq!(header2.material(0), Some(Material::Plastic));
+    assert_eq!(header2.material(100), None);
+    let glass = header2.material_index(&Material::Glass).unwrap();
+    assert_eq!(header2.add_material(Material::Glass), glass);
+    let materials = header2.materials.len() as u32;
+    assert_eq!(header2.add_material(Material::Hidden), materials);
+    assert_eq!(header2.material(materials), Some(Material::Hidden));
+
+    header2.brick_assets.clear();
+    assert_eq!(header2.brick_asset_index(&BrickAsset::Brick), None);
+    assert_eq!(header2.add_brick_asset(BrickAsset::Brick), 0);
+    assert_eq!(header2.add_brick_asset(BrickAsset::Wedge), 1);
+    assert_eq!(header2.add_brick_asset(BrickAsset::Brick), 0);
+    assert_eq!(header2.brick_asset(1), Some(BrickAsset::Wedge));
+    assert_eq!(header2.brick_assets, ["PB_DefaultBrick", "PB_DefaultWedge"]);
+}
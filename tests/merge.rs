@@ -0,0 +1,102 @@
+#![cfg(feature = "util")]
+
+use brickadia::{
+    save::{Brick, BrickColor, BrickOwner, SaveData, Size},
+    util::MAX_PROCEDURAL_SIZE,
+};
+use uuid::Uuid;
+
+/// A save of `asset` bricks of half extents (5, 5, 6) side by side along x, then y.
+fn row(asset: &str, x: i32, y: i32) -> SaveData {
+    let mut save = SaveData::default();
+    save.header2.brick_assets = vec![asset.into()];
+    for j in 0..y {
+        for i in 0..x {
+            save.bricks.push(Brick {
+                size: Size::Procedural(5, 5, 6),
+                position: (i * 10 + 5, j * 10 + 5, 6),
+                ..Default::default()
+            });
+        }
+    }
+    save.header1.brick_count = save.bricks.len() as u32;
+    save
+}
+
+#[test]
+fn merges_boxes() {
+    let mut save = row("PB_DefaultBrick", 4, 2);
+    assert_eq!(save.greedy_merge(), 7);
+    assert_eq!(save.bricks.len(), 1);
+    assert_eq!(save.bricks[0].size, Size::Procedural(20, 10, 6));
+    assert_eq!(save.bricks[0].position, (20, 10, 6));
+    assert_eq!(save.header1.brick_count, 1);
+
+    let mut save = row("PB_DefaultTile", 3, 1);
+    assert_eq!(save.greedy_merge(), 2);
+    assert_eq!(save.bricks[0].size, Size::Procedural(15, 5, 6));
+}
+
+#[test]
+fn keeps_other_shapes() {
+    for asset in [
+        "PB_DefaultRamp",
+        "PB_DefaultWedge",
+        "PB_DefaultSideWedge",
+        "PB_DefaultPole",
+    ] {
+        let mut save = row(asset, 4, 2);
+        assert_eq!(save.greedy_merge(), 0, "{}", asset);
+        assert_eq!(save.bricks.len(), 8);
+    }
+
+    // differing colors aren't merged
+    let mut save = row("PB_DefaultBrick", 2, 1);
+    save.bricks[1].color = BrickColor::Index(1);
+    assert_eq!(save.greedy_merge(), 0);
+}
+
+#[test]
+fn caps_merged_size() {
+    // 250 bricks in a row would be 1250 units wide
+    let mut save = row("PB_DefaultBrick", 250, 1);
+    save.greedy_merge();
+    for brick in save.bricks.iter() {
+        match brick.size {
+            Size::Procedural(x, y, z) => {
+                assert!(x <= MAX_PROCEDURAL_SIZE && y <= MAX_PROCEDURAL_SIZE && z <= 6);
+            }
+            Size::Empty => panic!("merged brick has no size"),
+        }
+    }
+    assert_eq!(save.bricks.len(), 3);
+    assert_eq!(save.bricks[0].size, Size::Procedural(500, 5, 6));
+
+    // bricks already at the limit are left alone
+    let mut save = row("PB_DefaultBrick", 2, 1);
+    save.bricks[0].size = Size::Procedural(MAX_PROCEDURAL_SIZE, 5, 6);
+    save.bricks[1].size = Size::Procedural(MAX_PROCEDURAL_SIZE, 5, 6);
+    save.bricks[1].position.0 = save.bricks[0].position.0 + 2 * MAX_PROCEDURAL_SIZE as i32;
+    assert_eq!(save.greedy_merge(), 0);
+}
+
+#[test]
+fn recounts_owners() {
+    let mut save = row("PB_DefaultBrick", 4, 2);
+    for (i, name) in ["First", "Second"].iter().enumerate() {
+        save.header2.brick_owners.push(BrickOwner {
+            name: name.to_string(),
+            id: Uuid::from_u128(i as u128 + 1),
+            bricks: 4,
+        });
+    }
+    for (i, brick) in save.bricks.iter_mut().enumerate() {
+        brick.owner_index = i as u32 / 4 + 1;
+    }
+
+    // bricks of different owners aren't merged together
+    assert_eq!(save.greedy_merge(), 6);
+    assert_eq!(save.bricks.len(), 2);
+    let counts: Vec<_> = save.header2.brick_owners.iter().map(|o| o.bricks).collect();
+    assert_eq!(counts, [1, 1]);
+}
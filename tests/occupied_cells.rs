@@ -0,0 +1,95 @@
+#![cfg(feature = "util")]
+
+use std::collections::HashMap;
+
+use brickadia::{
+    save::{Brick, Direction, Orientation, Rotation, Size},
+    util::{get_brick_size, BRICK_SIZE_MAP},
+};
+
+/// The minimum and maximum cells in `cells`.
+fn cell_bounds(cells: &[(i32, i32, i32)]) -> ((i32, i32, i32), (i32, i32, i32)) {
+    let min = |f: fn(&(i32, i32, i32)) -> i32| cells.iter().map(f).min().unwrap();
+    let max = |f: fn(&(i32, i32, i32)) -> i32| cells.iter().map(f).max().unwrap();
+    (
+        (min(|c| c.0), min(|c| c.1), min(|c| c.2)),
+        (max(|c| c.0), max(|c| c.1), max(|c| c.2)),
+    )
+}
+
+#[test]
+fn rotation_swaps_extents() {
+    let mut brick = Brick {
+        size: Size::Procedural(1, 2, 3),
+        position: (10, 20, 30),
+        ..Default::default()
+    };
+    let cells = brick.occupied_cells(&[], &HashMap::new());
+    assert_eq!(cells.len(), 2 * 4 * 6);
+    assert_eq!(cell_bounds(&cells), ((9, 18, 27), (10, 21, 32)));
+
+    // a quarter turn about Z swaps the X and Y extents
+    brick.set_orientation(Orientation::new(Direction::ZPositive, Rotation::Deg90));
+    let cells = brick.occupied_cells(&[], &HashMap::new());
+    assert_eq!(cells.len(), 2 * 4 * 6);
+    assert_eq!(cell_bounds(&cells), ((8, 19, 27), (11, 20, 32)));
+
+    // facing along X swaps the X and Z extents
+    brick.set_orientation(Orientation::new(Direction::XPositive, Rotation::Deg0));
+    let cells = brick.occupied_cells(&[], &HashMap::new());
+    assert_eq!(cell_bounds(&cells), ((7, 18, 29), (12, 21, 30)));
+
+    // facing along Y takes the Z extent to Y
+    brick.set_orientation(Orientation::new(Direction::YNegative, Rotation::Deg0));
+    let cells = brick.occupied_cells(&[], &HashMap::new());
+    assert_eq!(cell_bounds(&cells), ((8, 17, 29), (11, 22, 30)));
+}
+
+#[test]
+fn asset_sizes() {
+    let assets = vec!["B_1x1F_Round".to_string(), "B_Modded".to_string()];
+    let mut brick = Brick {
+        size: Size::Empty,
+        position: (0, 0, 2),
+        ..Default::default()
+    };
+    let cells = brick.occupied_cells(&assets, &BRICK_SIZE_MAP);
+    assert_eq!(cell_bounds(&cells), ((-5, -5, 0), (4, 4, 3)));
+
+    // unknown assets occupy nothing, unless their size is given
+    brick.asset_name_index = 1;
+    assert!(brick.occupied_cells(&assets, &BRICK_SIZE_MAP).is_empty());
+
+    let mut sizes = BRICK_SIZE_MAP.clone();
+    sizes.insert("B_Modded", (1, 1, 1));
+    assert_eq!(brick.occupied_cells(&assets, &sizes).len(), 8);
+}
+
+#[test]
+fn procedural_default_sizes() {
+    let assets = vec!["PB_DefaultBrick".to_string()];
+    let brick = Brick::default();
+
+    // procedural bricks have no static size, but resolve to the size they're placed at
+    assert_eq!(get_brick_size(&brick, &assets), (0, 0, 0));
+    assert!(brick.occupied_cells(&assets, &BRICK_SIZE_MAP).is_empty());
+    assert_eq!(Size::Empty.resolve("PB_DefaultBrick"), (5, 5, 6));
+    assert_eq!(
+        Size::Procedural(1, 2, 3).resolve("PB_DefaultBrick"),
+        (1, 2, 3)
+    );
+}
+
+#[test]
+fn oversized_bricks() {
+    let mut brick = Brick {
+        size: Size::Procedural(u32::MAX, 5, 5),
+        ..Default::default()
+    };
+    assert!(brick.occupied_cells(&[], &HashMap::new()).is_empty());
+
+    // extents that fit in i32 but overflow at the brick's position
+    brick.size = Size::Procedural(i32::MAX as u32, 1, 1);
+    brick.position = (1, 0, 0);
+    assert!(brick.occupied_cells(&[], &HashMap::new()).is_empty());
+}
@@ -0,0 +1,52 @@
+use std::convert::TryFrom;
+
+use brickadia::save::{Brick, Direction, Orientation, Rotation, SaveData};
+
+#[test]
+fn packing() {
+    for direction in 0..6u8 {
+        for rotation in 0..4u8 {
+            let orientation = Orientation::new(
+                Direction::try_from(direction).unwrap(),
+                Rotation::try_from(rotation).unwrap(),
+            );
+            let packed = orientation.packed();
+            assert_eq!(packed, (direction as u32) << 2 | rotation as u32);
+            assert_eq!(Orientation::from_packed(packed), orientation);
+        }
+    }
+
+    let orientation = Orientation::new(Direction::YNegative, Rotation::Deg270);
+    assert_eq!(orientation.packed(), 15);
+    // directions past the last wrap around
+    assert_eq!(
+        Orientation::from_packed(6 << 2 | 1),
+        Orientation::new(Direction::XPositive, Rotation::Deg90)
+    );
+}
+
+#[test]
+fn description() {
+    let orientation = Orientation::new(Direction::XPositive, Rotation::Deg90);
+    assert_eq!(orientation.describe(), "facing +X, rotated 90°");
+    assert_eq!(
+        Orientation::new(Direction::ZNegative, Rotation::Deg180).to_string(),
+        "facing -Z, rotated 180°"
+    );
+    assert_eq!(Direction::YPositive.to_string(), "+Y");
+    assert_eq!(Rotation::Deg270.to_string(), "270°");
+}
+
+#[test]
+fn written_orientation() {
+    let mut save = SaveData::default();
+    let mut brick = Brick::default();
+    brick.set_orientation(Orientation::new(Direction::YNegative, Rotation::Deg270));
+    save.bricks.push(brick);
+
+    let read = SaveData::from_bytes(&save.to_bytes().unwrap()).unwrap();
+    assert_eq!(
+        read.bricks[0].orientation(),
+        Orientation::new(Direction::YNegative, Rotation::Deg270)
+    );
+}
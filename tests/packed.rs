@@ -0,0 +1,106 @@
+use std::io::Cursor;
+
+use brickadia::{
+    read::SaveReader,
+    save::{Brick, Header2, SaveData, Size},
+    write::SaveWriter,
+};
+
+const INTS: [i32; 17] = [
+    0,
+    1,
+    -1,
+    63,
+    -63,
+    64,
+    -64,
+    8191,
+    -8191,
+    8192,
+    -8192,
+    1 << 20,
+    -(1 << 20),
+    i32::MAX,
+    -i32::MAX,
+    i32::MAX - 1,
+    i32::MIN + 1,
+];
+
+const UINTS: [u32; 14] = [
+    0,
+    1,
+    63,
+    64,
+    127,
+    128,
+    8191,
+    8192,
+    16383,
+    16384,
+    1 << 28,
+    i32::MAX as u32,
+    u32::MAX - 1,
+    u32::MAX,
+];
+
+fn round_trip(save: SaveData) -> SaveData {
+    let mut bytes = vec![];
+    SaveWriter::new(&mut bytes, save).write().unwrap();
+    SaveReader::new(Cursor::new(bytes))
+        .unwrap()
+        .read_all()
+        .unwrap()
+}
+
+#[test]
+fn int_packed_round_trip() {
+    let mut save = SaveData::default();
+    for &x in INTS.iter() {
+        for &y in INTS.iter() {
+            save.bricks.push(Brick {
+                position: (x, y, -x),
+                ..Default::default()
+            });
+        }
+    }
+
+    let read = round_trip(save);
+    let mut positions = read.bricks.iter().map(|b| b.position);
+    for &x in INTS.iter() {
+        for &y in INTS.iter() {
+            assert_eq!(positions.next(), Some((x, y, -x)));
+        }
+    }
+}
+
+#[test]
+fn uint_packed_round_trip() {
+    let mut save = SaveData {
+        header2: Header2::default().with_default_palette(),
+        ..Default::default()
+    };
+    for &x in UINTS.iter() {
+        save.bricks.push(Brick {
+            size: Size::Procedural(x, x / 2, u32::MAX - x),
+            owner_index: x,
+            ..Default::default()
+        });
+    }
+
+    let read = round_trip(save);
+    for (brick, &x) in read.bricks.iter().zip(UINTS.iter()) {
+        assert_eq!(brick.size, Size::Procedural(x, x / 2, u32::MAX - x));
+        assert_eq!(brick.owner_index, x);
+    }
+}
+
+#[test]
+fn int_packed_rejects_min() {
+    let mut save = SaveData::default();
+    save.bricks.push(Brick {
+        position: (i32::MIN, 0, 0),
+        ..Default::default()
+    });
+
+    assert!(SaveWriter::new(vec![], save).write().is_err());
+}
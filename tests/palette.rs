@@ -0,0 +1,124 @@
+#![cfg(feature = "util")]
+
+use brickadia::{
+    save::{Brick, BrickColor, Color, SaveData},
+    util::palette::median_cut,
+};
+
+fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::from_rgba(r, g, b, 255)
+}
+
+/// A save with one brick of each of `colors`, as unique colors.
+fn unique_save(colors: &[Color]) -> SaveData {
+    let mut save = SaveData::default();
+    for color in colors {
+        save.bricks.push(Brick {
+            color: BrickColor::Unique(color.clone()),
+            ..Default::default()
+        });
+    }
+    save
+}
+
+#[test]
+fn palettize_exact() {
+    let mut save = unique_save(&[rgb(255, 0, 0), rgb(0, 255, 0), rgb(255, 0, 0)]);
+    save.header2.colors = vec![rgb(0, 0, 255)];
+    save.bricks.push(Brick {
+        color: BrickColor::Index(0),
+        ..Default::default()
+    });
+
+    let report = save.palettize(16);
+    assert_eq!(report.colors_added, 2);
+    assert_eq!(report.bricks_converted, 3);
+
+    // existing entries are kept, and every brick gets its exact color
+    assert_eq!(save.header2.colors.len(), 3);
+    assert_eq!(save.header2.colors[0], rgb(0, 0, 255));
+    let color = |i: usize| match save.bricks[i].color {
+        BrickColor::Index(index) => save.header2.colors[index as usize].clone(),
+        BrickColor::Unique(_) => panic!("brick {} was not converted", i),
+    };
+    assert_eq!(color(0), rgb(255, 0, 0));
+    assert_eq!(color(1), rgb(0, 255, 0));
+    assert_eq!(color(2), rgb(255, 0, 0));
+    assert_eq!(color(3), rgb(0, 0, 255));
+
+    // nothing left to convert
+    assert_eq!(save.palettize(16), Default::default());
+}
+
+#[test]
+fn palettize_quantized() {
+    let colors = (0..64u8)
+        .map(|i| rgb(i * 4, 0, 255 - i * 4))
+        .collect::<Vec<_>>();
+    let mut save = unique_save(&colors);
+    save.header2.colors.clear();
+
+    let report = save.palettize(8);
+    assert_eq!(report.bricks_converted, 64);
+    assert!(report.colors_added <= 8);
+    assert!(report.bytes_saved > 0);
+    assert_eq!(save.header2.colors.len(), report.colors_added);
+    assert!(save
+        .bricks
+        .iter()
+        .all(|brick| matches!(brick.color, BrickColor::Index(i) if (i as usize) < 8)));
+}
+
+#[test]
+fn median_cut_colors() {
+    assert!(median_cut(&[], 4).is_empty());
+    assert!(median_cut(&[(rgb(1, 2, 3), 1)], 0).is_empty());
+
+    // two clusters split into one color each
+    let colors = [
+        (rgb(250, 0, 0), 3),
+        (rgb(255, 0, 0), 3),
+        (rgb(0, 0, 250), 3),
+        (rgb(0, 0, 255), 3),
+    ];
+    let mut palette = median_cut(&colors, 2);
+    palette.sort_by_key(|c| c.r);
+    assert_eq!(palette.len(), 2);
+    assert!(palette[0].b >= 250 && palette[0].r == 0);
+    assert!(palette[1].r >= 250 && palette[1].b == 0);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn palette_from_image() {
+    use image::{Rgba, RgbaImage};
+
+    // left half red, right half blue, with a slightly different red in one corner
+    let mut image = RgbaImage::from_fn(4, 2, |x, _| {
+        if x < 2 {
+            Rgba([255, 0, 0, 255])
+        } else {
+            Rgba([0, 0, 255, 255])
+        }
+    });
+    image.put_pixel(0, 0, Rgba([250, 0, 0, 255]));
+
+    let palette = SaveData::palette_from_image(&image, 2);
+    assert_eq!((palette.width, palette.height), (4, 2));
+    assert_eq!(palette.colors.len(), 2);
+    assert_eq!(palette.indices.len(), 8);
+
+    let color = |x, y| &palette.colors[palette.index(x, y).unwrap() as usize];
+    assert!(color(0, 0).r > 200 && color(0, 0).b == 0);
+    assert_eq!(color(0, 0), color(1, 1));
+    assert_eq!(color(3, 1), &rgb(0, 0, 255));
+    assert!(palette.index(4, 0).is_none());
+
+    // enough room for every color keeps them exact
+    let palette = SaveData::palette_from_image(&image, 16);
+    assert_eq!(palette.colors.len(), 3);
+    assert_eq!(
+        palette.colors[palette.index(0, 0).unwrap() as usize],
+        rgb(250, 0, 0)
+    );
+}
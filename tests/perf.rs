@@ -0,0 +1,53 @@
+#![cfg(feature = "util")]
+
+use brickadia::{
+    save::{Brick, BrickColor, Color, SaveData},
+    util::perf::{COMPONENT_COST, GLOW_COST, TRANSLUCENT_COST, UNIQUE_COLOR_COST},
+};
+
+/// A save with two plastic bricks, one glowing brick, one glass brick and one brick whose
+/// material is out of range.
+fn save() -> SaveData {
+    let mut save = SaveData::default();
+    save.header2.materials = vec!["BMC_Plastic".into(), "BMC_Glow".into(), "BMC_Glass".into()];
+    for material_index in [0, 0, 1, 2, 7] {
+        save.bricks.push(Brick {
+            material_index,
+            ..Default::default()
+        });
+    }
+    save
+}
+
+#[test]
+fn material_usage() {
+    let save = save();
+    let usage = save.material_usage();
+    assert_eq!(usage.len(), 3);
+    assert_eq!(usage["BMC_Plastic"], 2);
+    assert_eq!(usage["BMC_Glow"], 1);
+    assert_eq!(usage["BMC_Glass"], 1);
+}
+
+#[test]
+fn performance_score() {
+    let mut save = save();
+    let red = Color::from_rgba(255, 0, 0, 255);
+    save.bricks[0].color = BrickColor::Unique(red.clone());
+    save.bricks[1].color = BrickColor::Unique(red);
+    save.bricks[2].color = BrickColor::Unique(Color::from_rgba(0, 255, 0, 255));
+    save.bricks[3].set_component_property("BCD_PointLight", "Brightness", 100.0f32);
+
+    let score = save.performance_score();
+    assert_eq!(score.bricks.count, 5);
+    assert_eq!(score.glow.count, 1);
+    assert_eq!(score.translucent.count, 1);
+    assert_eq!(score.unique_colors.count, 2);
+    assert_eq!(score.components.count, 1);
+    assert_eq!(
+        score.total(),
+        5.0 + GLOW_COST + TRANSLUCENT_COST + 2.0 * UNIQUE_COLOR_COST + COMPONENT_COST
+    );
+
+    assert_eq!(SaveData::default().performance_score().total(), 0.0);
+}
@@ -0,0 +1,411 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
+
+use brickadia::{
+    compression::{write_compressed, Compression},
+    read::{brick_offsets, ReadError, SaveReader, TableSizes},
+    save::{Brick, BrickColor, Collision, Color, Component, Preview, PreviewError, SaveData, User},
+    write::{SaveWriter, MIN_SAVE_VERSION},
+};
+use uuid::Uuid;
+
+fn save_bytes() -> Vec<u8> {
+    let mut save = SaveData::default();
+    for i in 0..500 {
+        save.bricks.push(Brick {
+            position: (i, i * 3, 6),
+            ..Default::default()
+        });
+    }
+    save.to_bytes().unwrap()
+}
+
+#[test]
+fn truncated() {
+    let bytes = save_bytes();
+
+    // cutting the file anywhere fails cleanly, whether it is in a section's sizes or its data
+    for len in 0..bytes.len() {
+        match SaveData::from_bytes(&bytes[..len]) {
+            Err(ReadError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            Err(ReadError::CorruptSection { expected, got, .. }) => assert!(got <= expected),
+            result => panic!("truncated to {} bytes: {:?}", len, result.map(|_| ())),
+        }
+    }
+
+    // the bricks section is the last large one
+    match SaveData::from_bytes(&bytes[..bytes.len() - 20]) {
+        Err(ReadError::CorruptSection { section, .. }) => assert_eq!(section, "bricks"),
+        result => panic!("{:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn corrupt() {
+    let bytes = save_bytes();
+
+    // flipping a byte in the middle of the compressed bricks
+    let mut corrupted = bytes.clone();
+    let i = bytes.len() - 100;
+    corrupted[i] ^= 0xff;
+    match SaveData::from_bytes(&corrupted) {
+        Err(ReadError::CorruptSection {
+            section, expected, ..
+        }) => {
+            assert_eq!(section, "bricks");
+            assert!(expected > 0);
+        }
+        result => panic!("{:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn preview_format() {
+    let png = "examples/write_preview.png";
+    assert!(matches!(Preview::from_png_file(png), Ok(Preview::PNG(_))));
+    assert!(matches!(
+        Preview::from_jpeg_file(png),
+        Err(PreviewError::FormatMismatch { expected: "JPEG" })
+    ));
+
+    let jpeg: &[u8] = &[0xff, 0xd8, 0xff, 0xe0];
+    assert!(matches!(
+        Preview::from_jpeg_reader(jpeg),
+        Ok(Preview::JPEG(_))
+    ));
+    assert!(matches!(
+        Preview::from_png_reader(jpeg),
+        Err(PreviewError::FormatMismatch { expected: "PNG" })
+    ));
+}
+
+#[test]
+fn bricks_limited() {
+    let bytes = save_bytes();
+    let save = SaveData::from_bytes(&bytes).unwrap();
+
+    // only the start of the bricks section is needed, so a truncated file still reads
+    for (max, bytes) in [
+        (10, &bytes[..]),
+        (10, &bytes[..bytes.len() - 20]),
+        (1000, &bytes[..]),
+    ] {
+        let mut reader = SaveReader::new(bytes).unwrap();
+        let header1 = reader.read_header1().unwrap();
+        let header2 = reader.read_header2().unwrap();
+        reader.read_preview().unwrap();
+
+        let partial = reader.read_bricks_limited(&header1, &header2, max).unwrap();
+        assert_eq!(partial.total, 500);
+        assert_eq!(partial.is_truncated(), max < 500);
+        assert_eq!(partial.bricks, save.bricks[..max.min(500)]);
+    }
+}
+
+#[test]
+fn brick_layouts() {
+    let mut save = SaveData::default();
+    save.header2.physical_materials = vec!["BPMC_Default".into(), "BPMC_Ice".into()];
+    save.bricks.push(Brick {
+        position: (1, 2, 3),
+        collision: Collision {
+            player: false,
+            ..Collision::for_all(true)
+        },
+        physical_index: 1,
+        material_intensity: 7,
+        color: BrickColor::Unique(Color::from_rgba(10, 20, 30, 255)),
+        ..Default::default()
+    });
+    save.bricks.push(Brick {
+        position: (4, 5, 6),
+        ..Default::default()
+    });
+
+    // each version reads back what it can store
+    for version in MIN_SAVE_VERSION..=10 {
+        let mut bytes = vec![];
+        let mut written = save.clone();
+        if version < 10 {
+            written.bricks[0].collision = Collision::for_all(true);
+        }
+        SaveWriter::new(&mut bytes, &written)
+            .with_version(version)
+            .write()
+            .unwrap();
+
+        let read = SaveData::from_bytes(&bytes).unwrap();
+        assert_eq!(read.save_version, version);
+        assert_eq!(read.bricks[0].position, (1, 2, 3));
+        assert_eq!(read.bricks[0].collision, written.bricks[0].collision);
+        assert_eq!(read.bricks[0].color, written.bricks[0].color);
+        assert_eq!(read.bricks[1].position, (4, 5, 6));
+        if version >= 9 {
+            assert_eq!(read.bricks[0].physical_index, 1);
+            assert_eq!(read.bricks[0].material_intensity, 7);
+        } else {
+            assert_eq!(read.bricks[0].physical_index, 0);
+            assert_eq!(read.bricks[0].material_intensity, 5);
+        }
+    }
+}
+
+/// Assemble a save file from the header sections of `save`, with the `preview`, `bricks` and
+/// `components` sections given.
+fn assemble(save: &SaveData, preview: &[u8], bricks: &[u8], components: &[u8]) -> Vec<u8> {
+    let sections = SaveWriter::new(vec![], save).write_sections().unwrap();
+    let mut bytes = b"BRS".to_vec();
+    bytes.extend(10u16.to_le_bytes());
+    bytes.extend(0i32.to_le_bytes());
+    for section in [&sections.header1, &sections.header2] {
+        write_compressed(&mut bytes, section, Compression::none()).unwrap();
+    }
+    bytes.extend(preview);
+    write_compressed(&mut bytes, bricks, Compression::none()).unwrap();
+    write_compressed(&mut bytes, components, Compression::none()).unwrap();
+    bytes
+}
+
+#[test]
+fn corrupt_components() {
+    let mut one = SaveData::default();
+    one.bricks.push(Brick::default());
+    one.header1.brick_count = 1;
+    let valid = SaveWriter::new(vec![], &one).write_sections().unwrap();
+    assert!(SaveData::from_bytes(&assemble(&one, &[0], &valid.bricks, &valid.components)).is_ok());
+
+    // a component on the second brick of a two brick save, whose index still fits in the
+    // index width of a one brick save
+    let mut two = one.clone();
+    two.components.insert(
+        "BCD_Interact".into(),
+        Component {
+            version: 1,
+            brick_indices: vec![],
+            properties: BTreeMap::new(),
+        },
+    );
+    let mut brick = Brick::default();
+    brick
+        .components
+        .insert("BCD_Interact".into(), HashMap::new());
+    two.bricks.push(brick);
+    two.header1.brick_count = 2;
+    let components = SaveWriter::new(vec![], &two)
+        .write_sections()
+        .unwrap()
+        .components;
+    match SaveData::from_bytes(&assemble(&one, &[0], &valid.bricks, &components)) {
+        Err(ReadError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+        result => panic!("{:?}", result.map(|_| ())),
+    }
+
+    // a negative length of a component's data
+    let mut components = 1i32.to_le_bytes().to_vec();
+    components.extend(2i32.to_le_bytes());
+    components.extend(b"X\0");
+    components.extend((-1i32).to_le_bytes());
+    match SaveData::from_bytes(&assemble(&one, &[0], &valid.bricks, &components)) {
+        Err(ReadError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+        result => panic!("{:?}", result.map(|_| ())),
+    }
+
+    // a negative preview length
+    let mut preview = vec![1];
+    preview.extend((-1i32).to_le_bytes());
+    match SaveData::from_bytes(&assemble(&one, &preview, &valid.bricks, &valid.components)) {
+        Err(ReadError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+        result => panic!("{:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn lazy_matches_read_bricks() {
+    let mut save = SaveData::default();
+    for i in 0..20 {
+        let mut brick = Brick {
+            position: (i, 0, 6),
+            ..Default::default()
+        };
+        if i % 3 == 1 {
+            brick.set_component_property("BCD_Interact", "bPlayInteractSound", true);
+        }
+        save.bricks.push(brick);
+    }
+    let mut properties = BTreeMap::new();
+    properties.insert("bPlayInteractSound".into(), "Boolean".into());
+    save.components.insert(
+        "BCD_Interact".into(),
+        Component {
+            version: 1,
+            brick_indices: vec![],
+            properties,
+        },
+    );
+    save.header1.brick_count = 20;
+    let sections = SaveWriter::new(vec![], &save).write_sections().unwrap();
+
+    let bytes = assemble(&save, &[0], &sections.bricks, &sections.components);
+    let read = SaveData::from_bytes(&bytes).unwrap();
+    let lazy = SaveReader::new(&bytes[..]).unwrap().read_lazy().unwrap();
+    assert_eq!(lazy.len(), read.bricks.len());
+    for (i, brick) in read.bricks.iter().enumerate() {
+        assert_eq!(lazy.brick(i).as_ref(), Some(brick));
+    }
+
+    // the last brick is cut off, which fails both the same way
+    let truncated = &sections.bricks[..sections.bricks.len() - 2];
+    let bytes = assemble(&save, &[0], truncated, &sections.components);
+    let error = |result: Result<(), ReadError>| match result {
+        Err(ReadError::IoError(e)) => e.kind(),
+        result => panic!("{:?}", result),
+    };
+    assert_eq!(
+        error(SaveData::from_bytes(&bytes).map(|_| ())),
+        io::ErrorKind::UnexpectedEof
+    );
+    assert_eq!(
+        error(SaveReader::new(&bytes[..]).unwrap().read_lazy().map(|_| ())),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn unsupported_brick_layout() {
+    let save = save_bytes();
+    let mut reader = SaveReader::new(&save[..]).unwrap();
+    let header1 = reader.read_header1().unwrap();
+    let header2 = reader.read_header2().unwrap();
+    let sections = SaveWriter::new(vec![], SaveData::from_bytes(&save).unwrap())
+        .write_sections()
+        .unwrap();
+
+    let tables = TableSizes::new(10, &header2);
+    let count = header1.brick_count as usize;
+    assert_eq!(
+        brick_offsets(&sections.bricks, count, &tables)
+            .unwrap()
+            .len(),
+        500
+    );
+
+    for version in [0, 11] {
+        let tables = TableSizes::new(version, &header2);
+        for section in [&sections.bricks[..], &[]] {
+            assert!(matches!(
+                brick_offsets(section, count, &tables),
+                Err(ReadError::UnsupportedBrickLayout { version: v }) if v == version
+            ));
+        }
+    }
+}
+
+#[test]
+fn bricks_by_owner() {
+    let user = |id| User {
+        name: format!("User {}", id),
+        id: Uuid::from_u128(id),
+    };
+
+    let mut save = SaveData::default();
+    // the same user can appear as more than one owner
+    save.header2.brick_owners = vec![user(1).into(), user(2).into(), user(1).into()];
+    for i in 0..20u32 {
+        save.bricks.push(Brick {
+            position: (i as i32 * 10, 0, 6),
+            owner_index: i % 4,
+            ..Default::default()
+        });
+    }
+    let bytes = save.to_bytes().unwrap();
+
+    let by_owner = |id| {
+        let mut reader = SaveReader::new(&bytes[..]).unwrap();
+        let header1 = reader.read_header1().unwrap();
+        let header2 = reader.read_header2().unwrap();
+        reader.read_preview().unwrap();
+        reader
+            .bricks_by_owner(&header1, &header2, Uuid::from_u128(id))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    };
+
+    let first = by_owner(1);
+    assert_eq!(first.len(), 10);
+    assert!(first
+        .iter()
+        .all(|brick| brick.owner_index == 1 || brick.owner_index == 3));
+    assert_eq!(first[1].position, (30, 0, 6));
+
+    let second = by_owner(2);
+    assert_eq!(second.len(), 5);
+    assert!(second.iter().all(|brick| brick.owner_index == 2));
+
+    // public bricks belong to nobody
+    assert!(by_owner(0).is_empty());
+    assert!(by_owner(3).is_empty());
+}
+
+#[test]
+fn component_counts() {
+    let mut save = SaveData::default();
+    for (name, property) in [("BCD_Interact", "Message"), ("BCD_PointLight", "Color")] {
+        let mut properties = BTreeMap::new();
+        properties.insert(property.into(), "String".into());
+        save.components.insert(
+            name.into(),
+            Component {
+                version: 1,
+                brick_indices: vec![],
+                properties,
+            },
+        );
+    }
+    for i in 0..30 {
+        let mut brick = Brick {
+            position: (i * 10, 0, 6),
+            ..Default::default()
+        };
+        if i % 3 == 0 {
+            brick.set_component_property("BCD_Interact", "Message", "hi");
+        }
+        if i % 10 == 0 {
+            brick.set_component_property("BCD_PointLight", "Color", "red");
+        }
+        save.bricks.push(brick);
+    }
+    let bytes = save.to_bytes().unwrap();
+
+    let mut reader = SaveReader::new(&bytes[..]).unwrap();
+    // the sections before the bricks must be read first
+    assert!(matches!(
+        reader.component_counts(),
+        Err(ReadError::BadSectionReadOrder)
+    ));
+    reader.read_header1().unwrap();
+    reader.read_header2().unwrap();
+    reader.read_preview().unwrap();
+
+    let counts = reader.component_counts().unwrap();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts["BCD_Interact"], 10);
+    assert_eq!(counts["BCD_PointLight"], 3);
+
+    // a negative brick index count is rejected, as it is when reading the components
+    let mut bytes = vec![];
+    SaveWriter::uncompressed(&mut bytes, &save).write().unwrap();
+    let name = b"BCD_PointLight\0";
+    let count = bytes.windows(name.len()).position(|w| w == name).unwrap() + name.len() + 8;
+    bytes[count..count + 4].copy_from_slice(&(-1i32).to_le_bytes());
+
+    let mut reader = SaveReader::new(&bytes[..]).unwrap();
+    reader.read_header1().unwrap();
+    reader.read_header2().unwrap();
+    reader.read_preview().unwrap();
+    let invalid = |result: Result<_, ReadError>| matches!(result, Err(ReadError::IoError(e)) if e.kind() == io::ErrorKind::InvalidData);
+    assert!(invalid(reader.component_counts().map(|_| ())));
+    assert!(invalid(SaveData::from_bytes(&bytes).map(|_| ())));
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    io::Cursor,
+};
+
+use brickadia::{
+    read::SaveReader,
+    save::{
+        Brick, BrickColor, BrickOwner, Collision, Color, Component, Direction, Header1, Header2,
+        Preview, Rotation, SaveData, Size, UnrealValue, User,
+    },
+    write::SaveWriter,
+};
+use proptest::{collection::vec, option, prelude::*};
+use uuid::Uuid;
+
+/// A component's version and property types, by property name.
+type ComponentDef = (i32, BTreeMap<String, String>);
+
+fn name() -> impl Strategy<Value = String> {
+    "\\PC{0,12}"
+}
+
+fn color() -> impl Strategy<Value = Color> {
+    any::<[u8; 4]>().prop_map(|[r, g, b, a]| Color { r, g, b, a })
+}
+
+fn user() -> impl Strategy<Value = User> {
+    (any::<String>(), any::<u128>()).prop_map(|(name, id)| User {
+        name,
+        id: Uuid::from_u128(id),
+    })
+}
+
+fn header1() -> impl Strategy<Value = Header1> {
+    (
+        any::<String>(),
+        any::<String>(),
+        user(),
+        user(),
+        any::<[u8; 8]>(),
+        vec(any::<u8>(), 0..16),
+    )
+        .prop_map(
+            |(map, description, author, host, save_time, raw_extra)| Header1 {
+                map,
+                description,
+                author,
+                host: Some(host),
+                save_time,
+                brick_count: 0,
+                raw_extra,
+            },
+        )
+}
+
+fn header2() -> impl Strategy<Value = Header2> {
+    // owner brick counts are written as an i32
+    let owner = (user(), 0..=i32::MAX as u32).prop_map(|(user, bricks)| BrickOwner {
+        bricks,
+        ..BrickOwner::from(user)
+    });
+
+    (
+        vec(name(), 0..3),
+        vec(name(), 1..4),
+        vec(color(), 1..8),
+        vec(name(), 1..4),
+        vec(owner, 0..4),
+        vec(name(), 1..4),
+        vec(any::<u8>(), 0..16),
+    )
+        .prop_map(
+            |(
+                mods,
+                brick_assets,
+                colors,
+                materials,
+                brick_owners,
+                physical_materials,
+                raw_extra,
+            )| Header2 {
+                mods,
+                brick_assets,
+                colors,
+                materials,
+                brick_owners,
+                physical_materials,
+                raw_extra,
+            },
+        )
+}
+
+fn preview() -> impl Strategy<Value = Preview> {
+    prop_oneof![
+        Just(Preview::None),
+        vec(any::<u8>(), 0..64).prop_map(Preview::PNG),
+        vec(any::<u8>(), 0..64).prop_map(Preview::JPEG),
+    ]
+}
+
+fn type_name() -> impl Strategy<Value = String> {
+    prop::sample::select(vec![
+        "Class", "String", "Boolean", "Float", "Color", "Byte", "Rotator",
+    ])
+    .prop_map(String::from)
+}
+
+fn unreal_value(type_name: &str) -> BoxedStrategy<UnrealValue> {
+    // NaN never compares equal, so leave it out
+    let float = || any::<f32>().prop_filter("NaN", |f| !f.is_nan());
+
+    match type_name {
+        "Class" => any::<String>().prop_map(UnrealValue::Class).boxed(),
+        "String" => any::<String>().prop_map(UnrealValue::String).boxed(),
+        "Boolean" => any::<bool>().prop_map(UnrealValue::Boolean).boxed(),
+        "Float" => float().prop_map(UnrealValue::Float).boxed(),
+        "Color" => color().prop_map(UnrealValue::Color).boxed(),
+        "Byte" => any::<u8>().prop_map(UnrealValue::Byte).boxed(),
+        "Rotator" => (float(), float(), float())
+            .prop_map(|(x, y, z)| UnrealValue::Rotator(x, y, z))
+            .boxed(),
+        _ => unreachable!(),
+    }
+}
+
+fn brick(header2: &Header2, components: &HashMap<String, ComponentDef>) -> BoxedStrategy<Brick> {
+    let size = prop_oneof![
+        Just(Size::Empty),
+        any::<(u32, u32, u32)>().prop_map(|(x, y, z)| Size::Procedural(x, y, z)),
+    ];
+    // the writer can't pack i32::MIN
+    let int = || (i32::MIN + 1)..=i32::MAX;
+    let color = prop_oneof![
+        (0..header2.colors.len() as u32).prop_map(BrickColor::Index),
+        any::<[u8; 3]>().prop_map(|[r, g, b]| BrickColor::Unique(Color { r, g, b, a: 255 })),
+    ];
+
+    // each component is either on the brick, with a value for each property, or not
+    let brick_components = components
+        .iter()
+        .map(|(component, (_, properties))| {
+            let values = properties
+                .iter()
+                .map(|(property, type_name)| {
+                    let property = property.clone();
+                    unreal_value(type_name).prop_map(move |value| (property.clone(), value))
+                })
+                .collect::<Vec<_>>();
+            let component = component.clone();
+            option::of(values.prop_map(move |values| {
+                (
+                    component.clone(),
+                    values.into_iter().collect::<HashMap<_, _>>(),
+                )
+            }))
+        })
+        .collect::<Vec<_>>()
+        .prop_map(|components| components.into_iter().flatten().collect::<HashMap<_, _>>());
+
+    (
+        (
+            0..header2.brick_assets.len() as u32,
+            size,
+            (int(), int(), int()),
+            0..6u8,
+            0..4u8,
+            any::<[bool; 4]>(),
+            any::<bool>(),
+        ),
+        (
+            0..header2.materials.len() as u32,
+            0..header2.physical_materials.len() as u32,
+            0..=10u32,
+            color,
+            0..=header2.brick_owners.len() as u32,
+            brick_components,
+        ),
+    )
+        .prop_map(
+            |(
+                (asset_name_index, size, position, direction, rotation, collision, visibility),
+                (
+                    material_index,
+                    physical_index,
+                    material_intensity,
+                    color,
+                    owner_index,
+                    components,
+                ),
+            )| {
+                let [player, weapon, interaction, tool] = collision;
+                Brick {
+                    asset_name_index,
+                    size,
+                    position,
+                    direction: Direction::try_from(direction).unwrap(),
+                    rotation: Rotation::try_from(rotation).unwrap(),
+                    collision: Collision {
+                        player,
+                        weapon,
+                        interaction,
+                        tool,
+                    },
+                    visibility,
+                    material_index,
+                    physical_index,
+                    material_intensity,
+                    color,
+                    owner_index,
+                    components,
+                }
+            },
+        )
+        .boxed()
+}
+
+fn save_data() -> impl Strategy<Value = SaveData> {
+    let components = prop::collection::hash_map(
+        name(),
+        (
+            any::<i32>(),
+            prop::collection::btree_map(name(), type_name(), 0..4),
+        ),
+        0..3,
+    );
+
+    (header1(), header2(), components)
+        .prop_flat_map(|(header1, header2, components)| {
+            let bricks = vec(brick(&header2, &components), 0..32);
+            (
+                Just(header1),
+                Just(header2),
+                Just(components),
+                bricks,
+                preview(),
+                any::<i32>(),
+            )
+        })
+        .prop_map(
+            |(mut header1, header2, components, bricks, preview, game_version)| {
+                header1.brick_count = bricks.len() as u32;
+                let mut data = SaveData {
+                    game_version,
+                    header1,
+                    header2,
+                    preview,
+                    bricks,
+                    components: components
+                        .into_iter()
+                        .map(|(name, (version, properties))| {
+                            let component = Component {
+                                version,
+                                brick_indices: vec![],
+                                properties,
+                            };
+                            (name, component)
+                        })
+                        .collect(),
+                    ..Default::default()
+                };
+                data.reconcile_components();
+                data
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn read_write_read(data in save_data(), compressed in any::<bool>()) {
+        let mut bytes = vec![];
+        if compressed {
+            SaveWriter::new(&mut bytes, &data).write().unwrap();
+        } else {
+            SaveWriter::uncompressed(&mut bytes, &data).write().unwrap();
+        }
+        let read = SaveReader::new(Cursor::new(&bytes)).unwrap().read_all().unwrap();
+
+        // components on no bricks aren't written
+        let mut expected = data;
+        expected.components.retain(|_, component| !component.brick_indices.is_empty());
+        prop_assert_eq!(&read, &expected);
+
+        // writing what was read gives back the same save
+        let mut rewritten = vec![];
+        SaveWriter::uncompressed(&mut rewritten, &read).write().unwrap();
+        let reread = SaveReader::new(Cursor::new(&rewritten)).unwrap().read_all().unwrap();
+        prop_assert_eq!(&reread, &expected);
+    }
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn serde_raw_extra() {
+    let mut save = SaveData::default();
+    save.header1.raw_extra = vec![1, 2, 3];
+    save.header2.raw_extra = vec![4, 5];
+
+    let json = serde_json::to_value(&save).unwrap();
+    assert_eq!(json["header1_raw_extra"], serde_json::json!([1, 2, 3]));
+    assert_eq!(json["header2_raw_extra"], serde_json::json!([4, 5]));
+
+    let read: SaveData = serde_json::from_value(json).unwrap();
+    assert_eq!(read, save);
+}
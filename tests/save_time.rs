@@ -0,0 +1,80 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use brickadia::save::Header1;
+
+// 2021-06-24T18:42:49.135Z is 637601569691350000 ticks of 100ns since 0001-01-01
+const UNIX_SECONDS: u64 = 1_624_560_169;
+const MILLIS: u32 = 135;
+const BYTES: [u8; 8] = [240, 51, 175, 221, 63, 55, 217, 8];
+
+#[test]
+fn system_time() {
+    let time = UNIX_EPOCH + Duration::new(UNIX_SECONDS, MILLIS * 1_000_000);
+
+    let mut header1 = Header1::default();
+    header1.set_save_time(time);
+    assert_eq!(header1.save_time, BYTES);
+    assert_eq!(header1.saved_at(), Some(time));
+
+    // the epoch is 0001-01-01, and earlier times are clamped to it
+    header1.save_time = [0; 8];
+    let epoch = header1.saved_at();
+    if let Some(epoch) = epoch {
+        header1.set_save_time(epoch - Duration::from_secs(1));
+        assert_eq!(header1.save_time, [0; 8]);
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_datetime() {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    let time: DateTime<Utc> = Utc
+        .timestamp_opt(UNIX_SECONDS as i64, MILLIS * 1_000_000)
+        .unwrap();
+
+    let mut header1 = Header1::default();
+    header1.set_save_datetime(time);
+    assert_eq!(header1.save_time, BYTES);
+    assert_eq!(header1.saved_at_datetime(), Some(time));
+
+    header1.save_time = [0; 8];
+    assert_eq!(
+        header1.saved_at_datetime().unwrap().to_rfc3339(),
+        "0001-01-01T00:00:00+00:00"
+    );
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn serde_round_trip() {
+    use brickadia::save::SaveData;
+
+    let mut save = SaveData::default();
+    save.header1.save_time = BYTES;
+    let json = serde_json::to_value(&save).unwrap();
+    assert_eq!(json["save_time"], "2021-06-24T18:42:49.1350000Z");
+    assert_eq!(serde_json::from_value::<SaveData>(json).unwrap(), save);
+
+    let parse = |time: &str| {
+        let mut json = serde_json::to_value(&save).unwrap();
+        json["save_time"] = time.into();
+        serde_json::from_value::<SaveData>(json).map(|s| s.header1.save_time)
+    };
+    assert_eq!(parse("0001-01-01T00:00:00Z").unwrap(), [0; 8]);
+    assert_eq!(parse("2021-06-24T18:42:49.135Z").unwrap(), BYTES);
+    for invalid in [
+        "2021-13-01T00:00:00Z",
+        "2021-02-30T00:00:00Z",
+        "2021-06-00T00:00:00Z",
+        "2021-06-24T24:00:00Z",
+        "2021-06-24T18:60:00Z",
+        "2021-06-24T18:42:60Z",
+        "2021-06-24T18:42:49.-1Z",
+        "99999-12-31T23:59:59Z",
+        "9223372036854775807-01-01T00:00:00Z",
+    ] {
+        assert!(parse(invalid).is_err(), "{}", invalid);
+    }
+}
@@ -0,0 +1,68 @@
+use brickadia::{
+    save::{Brick, SaveData},
+    shared::{SharedSave, CHUNK_SIZE},
+};
+
+/// A save with `count` bricks, each at x = its index.
+fn save(count: usize) -> SaveData {
+    let mut save = SaveData::default();
+    for i in 0..count {
+        save.bricks.push(Brick {
+            position: (i as i32, 0, 0),
+            ..Default::default()
+        });
+    }
+    save
+}
+
+#[test]
+fn round_trip() {
+    let data = save(CHUNK_SIZE * 2 + 10);
+    let shared = data.clone().into_shared();
+    assert_eq!(shared.len(), data.bricks.len());
+    assert!(!shared.is_empty());
+    assert_eq!(
+        shared.brick(CHUNK_SIZE + 3).unwrap().position.0,
+        CHUNK_SIZE as i32 + 3
+    );
+    assert!(shared.brick(data.bricks.len()).is_none());
+    assert!(shared.bricks().eq(data.bricks.iter()));
+    assert_eq!(shared.into_save_data(), data);
+
+    assert!(SharedSave::new(SaveData::default()).is_empty());
+}
+
+#[test]
+fn copy_on_write() {
+    let original = SharedSave::from(save(CHUNK_SIZE * 3));
+    let mut edited = original.clone();
+    assert!(edited.diverged_ranges(&original).is_empty());
+
+    // editing a brick only copies its chunk
+    edited.brick_mut(CHUNK_SIZE + 1).unwrap().position.2 = 100;
+    let ranges = edited.diverged_ranges(&original);
+    assert_eq!(ranges.len(), 1);
+    assert_eq!(ranges[0], CHUNK_SIZE..CHUNK_SIZE * 2);
+    assert_eq!(original.brick(CHUNK_SIZE + 1).unwrap().position.2, 0);
+    assert_eq!(edited.brick(CHUNK_SIZE + 1).unwrap().position.2, 100);
+
+    // appending starts a new chunk once the last is full
+    edited.push(Brick::default());
+    assert_eq!(edited.len(), CHUNK_SIZE * 3 + 1);
+    assert_eq!(
+        edited.diverged_ranges(&original),
+        [
+            CHUNK_SIZE..CHUNK_SIZE * 2,
+            CHUNK_SIZE * 3..CHUNK_SIZE * 3 + 1
+        ]
+    );
+
+    // headers are copied on write too
+    edited.header1_mut().description = "Edited".into();
+    assert_eq!(original.header1().description, "");
+    assert_eq!(edited.header1().description, "Edited");
+
+    let data = SaveData::from(edited);
+    assert_eq!(data.bricks.len(), CHUNK_SIZE * 3 + 1);
+    assert_eq!(data.header1.description, "Edited");
+}
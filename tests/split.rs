@@ -0,0 +1,86 @@
+#![cfg(feature = "util")]
+
+use std::collections::BTreeMap;
+
+use brickadia::save::{Brick, BrickColor, Color, Component, SaveData, Size, User};
+use uuid::Uuid;
+
+#[test]
+fn split_by_z() {
+    let mut save = SaveData::default();
+    save.header2.brick_assets = vec!["PB_DefaultBrick".into(), "PB_DefaultTile".into()];
+    save.header2.colors = vec![
+        Color::from_rgba(255, 0, 0, 255),
+        Color::from_rgba(0, 0, 255, 255),
+    ];
+    save.header2.brick_owners = vec![
+        User {
+            name: "Low".into(),
+            id: Uuid::from_u128(1),
+        }
+        .into(),
+        User {
+            name: "High".into(),
+            id: Uuid::from_u128(2),
+        }
+        .into(),
+    ];
+
+    // bases at 0, 10, 100 and 94: the last spans the boundary, but starts in the first slab
+    for (i, &(z, half_height)) in [(6, 6), (16, 6), (106, 6), (100, 6)].iter().enumerate() {
+        let high = z >= 100;
+        let mut brick = Brick {
+            asset_name_index: high as u32,
+            size: Size::Procedural(5, 5, half_height),
+            position: (i as i32 * 10, 0, z),
+            color: BrickColor::Index(high as u32),
+            owner_index: high as u32 + 1,
+            ..Default::default()
+        };
+        if i == 2 {
+            brick.set_component_property("BCD_PointLight", "Brightness", 50.0f32);
+        }
+        save.bricks.push(brick);
+    }
+
+    let mut properties = BTreeMap::new();
+    properties.insert("Brightness".into(), "Float".into());
+    save.components.insert(
+        "BCD_PointLight".into(),
+        Component {
+            version: 1,
+            brick_indices: vec![2],
+            properties,
+        },
+    );
+
+    let slabs = save.split_by_z(100);
+    assert_eq!(slabs.len(), 2);
+    assert_eq!(slabs[0].bricks.len(), 3);
+    assert_eq!(slabs[1].bricks.len(), 1);
+    assert_eq!(slabs[0].header1.brick_count, 3);
+
+    // each slab only keeps the table entries and owners its bricks use
+    let high = &slabs[1];
+    assert_eq!(high.header2.brick_assets, ["PB_DefaultTile"]);
+    assert_eq!(high.header2.colors, [Color::from_rgba(0, 0, 255, 255)]);
+    assert_eq!(high.header2.brick_owners.len(), 1);
+    assert_eq!(high.header2.brick_owners[0].name, "High");
+    assert_eq!(high.header2.brick_owners[0].bricks, 1);
+    assert_eq!(high.bricks[0].asset_name_index, 0);
+    assert_eq!(high.bricks[0].color, BrickColor::Index(0));
+    assert_eq!(high.bricks[0].owner_index, 1);
+    assert_eq!(high.components["BCD_PointLight"].brick_indices, [0]);
+    assert!(slabs[0].components.is_empty());
+
+    let low = &slabs[0];
+    assert_eq!(low.header2.brick_owners.len(), 2);
+    assert_eq!(low.header2.brick_owners[0].bricks, 2);
+    assert_eq!(low.header2.brick_owners[1].bricks, 1);
+}
+
+#[test]
+#[should_panic(expected = "band_height must be positive")]
+fn zero_band_height() {
+    SaveData::default().split_by_z(0);
+}
@@ -0,0 +1,78 @@
+use std::io::Cursor;
+
+use brickadia::{
+    read::SaveReader,
+    save::{Brick, BrickColor, Color, Header2, SaveData},
+    write::SaveWriter,
+};
+
+fn names(prefix: &str, len: usize) -> Vec<String> {
+    (0..len).map(|i| format!("{}{}", prefix, i)).collect()
+}
+
+/// A save whose `Header2` tables have the given lengths, with bricks using the last entry of
+/// each table (or index 0 for empty tables). Every brick is followed by another, so reading
+/// an index at the wrong width shows up in the bricks after it.
+fn save(assets: usize, materials: usize, physical_materials: usize, colors: usize) -> SaveData {
+    let last = |len: usize| len.saturating_sub(1) as u32;
+
+    let mut save = SaveData {
+        header2: Header2 {
+            brick_assets: names("PB_Asset", assets),
+            materials: names("BMC_Material", materials),
+            physical_materials: names("BPMC_Physical", physical_materials),
+            colors: (0..colors as u8)
+                .map(|i| Color {
+                    r: i,
+                    g: i,
+                    b: i,
+                    a: 255,
+                })
+                .collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    for i in 0..3 {
+        save.bricks.push(Brick {
+            asset_name_index: last(assets),
+            position: (i, -i, i * 1000),
+            material_index: last(materials),
+            physical_index: last(physical_materials),
+            material_intensity: 10,
+            color: BrickColor::Index(last(colors)),
+            owner_index: i as u32,
+            ..Default::default()
+        });
+    }
+    save.header1.brick_count = save.bricks.len() as u32;
+    save
+}
+
+#[test]
+fn small_tables_round_trip() {
+    for assets in 0..=2 {
+        for materials in 0..=2 {
+            for physical_materials in 0..=2 {
+                for colors in 0..=2 {
+                    let save = save(assets, materials, physical_materials, colors);
+
+                    let mut bytes = vec![];
+                    SaveWriter::uncompressed(&mut bytes, &save).write().unwrap();
+                    let read = SaveReader::new(Cursor::new(bytes))
+                        .unwrap()
+                        .read_all()
+                        .unwrap();
+
+                    assert_eq!(
+                        read.bricks, save.bricks,
+                        "{} assets, {} materials, {} physical materials, {} colors",
+                        assets, materials, physical_materials, colors
+                    );
+                    assert_eq!(read.header2, save.header2);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "util")]
+
+use brickadia::save::{Brick, Direction, Orientation, Rotation, SaveData, Size};
+
+/// A save with one long brick along X, and one facing +X.
+fn save() -> SaveData {
+    let mut save = SaveData::default();
+    save.bricks.push(Brick {
+        size: Size::Procedural(20, 5, 6),
+        position: (30, 0, 6),
+        ..Default::default()
+    });
+    let mut brick = Brick {
+        size: Size::Procedural(5, 5, 2),
+        position: (-10, 20, 40),
+        ..Default::default()
+    };
+    brick.set_orientation(Orientation::new(Direction::XPositive, Rotation::Deg90));
+    save.bricks.push(brick);
+    save
+}
+
+#[test]
+fn translate() {
+    let mut moved = save();
+    moved.translate((5, -5, 10));
+    assert_eq!(moved.bricks[0].position, (35, -5, 16));
+    assert_eq!(moved.bricks[1].position, (-5, 15, 50));
+
+    // wrapping around and back gives the original positions
+    moved.translate((i32::MAX, i32::MAX, i32::MAX));
+    moved.translate((-i32::MAX, -i32::MAX, -i32::MAX));
+    moved.translate((-5, 5, -10));
+    assert_eq!(moved, save());
+}
+
+#[test]
+fn rotate() {
+    let mut rotated = save();
+    rotated.bricks.truncate(1);
+    rotated.rotate(1);
+
+    // +X turns toward +Y, and the brick's extents turn with it
+    assert_eq!(rotated.bricks[0].position, (0, 30, 6));
+    assert_eq!(rotated.bounds(), Some(((-5, 10, 0), (5, 50, 12))));
+
+    rotated.rotate(2);
+    assert_eq!(rotated.bricks[0].position, (0, -30, 6));
+    assert_eq!(rotated.bounds(), Some(((-5, -50, 0), (5, -10, 12))));
+
+    // a full turn changes nothing
+    let mut rotated = save();
+    let bounds = rotated.bounds();
+    rotated.rotate(4);
+    assert_eq!(rotated, save());
+    rotated.rotate(1);
+    assert_eq!(rotated.bricks[1].position, (-20, -10, 40));
+    assert_eq!(rotated.bricks[1].direction, Direction::YPositive);
+    rotated.rotate(3);
+    assert_eq!(rotated, save());
+    assert_eq!(rotated.bounds(), bounds);
+}
+
+#[test]
+fn bounds_at_limits() {
+    let mut save = SaveData::default();
+    save.bricks.push(Brick {
+        size: Size::Procedural(u32::MAX, 5, 6),
+        position: (0, i32::MAX, i32::MIN),
+        ..Default::default()
+    });
+
+    // corners past the limits of an i32 are clamped rather than overflowing
+    assert_eq!(
+        save.bounds(),
+        Some((
+            (i32::MIN, i32::MAX - 5, i32::MIN),
+            (i32::MAX, i32::MAX, i32::MIN + 6)
+        ))
+    );
+
+    // translating to the limits wraps, and the bounds stay valid
+    save.bricks[0].size = Size::Procedural(5, 5, 6);
+    save.translate((i32::MAX, 1, 0));
+    assert_eq!(save.bricks[0].position, (i32::MAX, i32::MIN, i32::MIN));
+    assert_eq!(
+        save.bounds(),
+        Some((
+            (i32::MAX - 5, i32::MIN, i32::MIN),
+            (i32::MAX, i32::MIN + 5, i32::MIN + 6)
+        ))
+    );
+}
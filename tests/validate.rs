@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use brickadia::{
+    read::SaveReader,
+    save::{Brick, Color, Component, SaveData, UnrealValue},
+    validate::{FeatureKind, FeatureTable, Incompatibility, ValidationError},
+    write::{SaveWriter, WriteError},
+};
+
+#[test]
+fn feature_table() {
+    let mut save = SaveData::default();
+    save.header2.materials = vec!["BMC_Plastic".into(), "BMC_Glow".into()];
+    save.header2.brick_assets = vec!["PB_DefaultBrick".into(), "B_Pumpkin".into()];
+    save.bricks.push(Brick {
+        material_index: 1,
+        ..Default::default()
+    });
+    let mut brick = Brick::default();
+    brick.set_component_property("BCD_PointLight", "Brightness", 100.0f32);
+    save.bricks.push(brick);
+
+    let mut table = FeatureTable::default();
+    table.materials.insert("BMC_Glow".into(), 3000);
+    table.assets.insert("B_Pumpkin".into(), 5000);
+    table.components.insert("BCD_PointLight".into(), 4000);
+
+    // the pumpkin asset isn't used by any brick
+    assert_eq!(
+        save.validate_with_table(3500, &table),
+        [Incompatibility {
+            kind: FeatureKind::Component,
+            name: "BCD_PointLight".into(),
+            introduced: 4000,
+        }]
+    );
+    assert_eq!(save.validate_with_table(2000, &table).len(), 2);
+    assert!(save.validate_with_table(4000, &table).is_empty());
+
+    // features missing from the table exist in every version
+    assert!(save
+        .validate_with_table(0, &FeatureTable::default())
+        .is_empty());
+}
+
+#[test]
+fn physical_materials_by_version() {
+    let mut save = SaveData::default();
+    save.header2.colors = vec![Color::from_rgba(255, 255, 255, 255)];
+    save.bricks.push(Brick {
+        physical_index: 1,
+        ..Default::default()
+    });
+    save.header1.brick_count = 1;
+    assert!(matches!(
+        save.validate(),
+        Err(ValidationError::IndexOutOfRange {
+            field: "physical_index",
+            ..
+        })
+    ));
+
+    // an empty table is allowed, as the writer pads it
+    save.header2.physical_materials.clear();
+    assert!(save.validate().is_ok());
+
+    // version 8 saves have no physical materials at all
+    let mut bytes = vec![];
+    save.header2.physical_materials = vec!["BPMC_Default".into()];
+    save.bricks[0].physical_index = 0;
+    SaveWriter::new(&mut bytes, save)
+        .with_version(8)
+        .write()
+        .unwrap();
+    let read = SaveReader::new(&bytes[..]).unwrap().read_all().unwrap();
+    assert_eq!(read.save_version, 8);
+    assert!(read.header2.physical_materials.is_empty());
+    assert!(read.validate().is_ok());
+}
+
+#[test]
+fn component_values() {
+    let mut save = SaveData::default();
+    save.header2.colors = vec![Color::from_rgba(255, 255, 255, 255)];
+    let mut properties = BTreeMap::new();
+    properties.insert("Brightness".into(), "Float".into());
+    properties.insert("Rotation".into(), "Rotator".into());
+    properties.insert("Target".into(), "Object".into());
+    save.components.insert(
+        "BCD_Light".into(),
+        Component {
+            version: 1,
+            brick_indices: vec![],
+            properties,
+        },
+    );
+    let mut brick = Brick::default();
+    brick.set_component_property("BCD_Light", "Brightness", 1.0f32);
+    brick.set_component_property(
+        "BCD_Light",
+        "Rotation",
+        UnrealValue::Rotator(0.0, 90.0, 0.0),
+    );
+    // objects are read back as classes
+    brick.set_component_property("BCD_Light", "Target", UnrealValue::Class("None".into()));
+    save.bricks.push(brick);
+    save.validate().unwrap();
+
+    let check = |edit: &dyn Fn(&mut Brick)| {
+        let mut save = save.clone();
+        edit(&mut save.bricks[0]);
+        save.validate().unwrap_err()
+    };
+
+    assert_eq!(
+        check(&|brick| {
+            brick.set_component_property("BCD_Other", "Value", true);
+        }),
+        ValidationError::UnknownComponent {
+            brick: 0,
+            component: "BCD_Other".into(),
+        }
+    );
+    assert_eq!(
+        check(&|brick| {
+            brick
+                .components
+                .get_mut("BCD_Light")
+                .unwrap()
+                .remove("Rotation");
+        }),
+        ValidationError::MissingProperty {
+            brick: 0,
+            component: "BCD_Light".into(),
+            property: "Rotation".into(),
+        }
+    );
+    assert_eq!(
+        check(&|brick| {
+            brick.set_component_property("BCD_Light", "Brightness", true);
+        }),
+        ValidationError::PropertyTypeMismatch {
+            brick: 0,
+            component: "BCD_Light".into(),
+            property: "Brightness".into(),
+            expected: "Float".into(),
+            found: "Boolean",
+        }
+    );
+    assert_eq!(
+        check(&|brick| {
+            brick.set_component_property("BCD_Light", "Brightness", f32::NAN);
+        }),
+        ValidationError::NonFiniteFloat {
+            brick: 0,
+            component: "BCD_Light".into(),
+            property: "Brightness".into(),
+        }
+    );
+    assert!(matches!(
+        check(&|brick| {
+            brick.set_component_property(
+                "BCD_Light",
+                "Rotation",
+                UnrealValue::Rotator(0.0, f32::INFINITY, 0.0),
+            );
+        }),
+        ValidationError::NonFiniteFloat { .. }
+    ));
+
+    // write_validated refuses to write an invalid save
+    let mut invalid = save.clone();
+    invalid.bricks[0].set_component_property("BCD_Light", "Brightness", f32::INFINITY);
+    assert!(matches!(
+        SaveWriter::new(vec![], &invalid).write_validated(),
+        Err(WriteError::Invalid(ValidationError::NonFiniteFloat { .. }))
+    ));
+}
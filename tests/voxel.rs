@@ -0,0 +1,46 @@
+#![cfg(feature = "util")]
+
+use brickadia::{
+    save::{BrickColor, Color, SaveData, Size},
+    util::voxel::VoxelGrid,
+};
+
+#[test]
+fn cell_positions() {
+    let red = Color::from_rgba(255, 0, 0, 255);
+    let mut grid = VoxelGrid::new((4, 3, 2), (-100, 0, 50), (10, 10, 4));
+    grid.set((0, 0, 0), Some(red.clone()));
+    grid.set((3, 2, 1), Some(red.clone()));
+    grid.set((1, 0, 0), Some(Color::from_rgba(0, 0, 255, 255)));
+
+    let save = SaveData::from_voxel_grid(&grid);
+    assert_eq!(save.header2.colors.len(), 2);
+    assert_eq!(save.header1.brick_count, 3);
+    let brick = |position| {
+        save.bricks
+            .iter()
+            .find(|brick| brick.position == position)
+            .unwrap()
+    };
+    assert_eq!(brick((-95, 5, 52)).size, Size::Procedural(5, 5, 2));
+    assert_eq!(brick((-65, 25, 56)).color, brick((-95, 5, 52)).color);
+    assert_eq!(brick((-85, 5, 52)).color, BrickColor::Index(1));
+}
+
+#[test]
+fn large_grids() {
+    // cells far from the origin, whose positions would overflow u32 arithmetic
+    let mut grid = VoxelGrid::new((1, 1, 3), (0, 0, i32::MIN), (10, 10, 1 << 30));
+    grid.set((0, 0, 2), Some(Color::from_rgba(255, 255, 255, 255)));
+    let save = SaveData::from_voxel_grid(&grid);
+    assert_eq!(save.bricks[0].position, (5, 5, 1 << 29));
+
+    let grid = VoxelGrid::new((1, 1, 1), (i32::MAX - 10, 0, 0), (10, 10, 10));
+    assert_eq!(grid.dimensions(), (1, 1, 1));
+}
+
+#[test]
+#[should_panic(expected = "voxel grid is too large")]
+fn grid_past_max_position() {
+    VoxelGrid::new((2, 1, 1), (i32::MAX - 10, 0, 0), (10, 10, 10));
+}
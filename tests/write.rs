@@ -0,0 +1,627 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryInto,
+    io::{Cursor, Read, Write},
+};
+
+use brickadia::{
+    read::SaveReader,
+    save::{Brick, BrickColor, BrickOwner, Color, Component, Preview, SaveData, UnrealValue, User},
+    write::{SaveWriter, WriteError, WriteStage},
+};
+use uuid::Uuid;
+
+/// Split an uncompressed, preview-less save into its header 1, header 2, bricks, and components sections.
+fn sections(bytes: &[u8]) -> Vec<&[u8]> {
+    fn section(bytes: &[u8]) -> (&[u8], &[u8]) {
+        let len = i32::from_le_bytes(bytes[0..4].try_into().unwrap()) as usize;
+        assert_eq!(&bytes[4..8], &[0, 0, 0, 0], "section is compressed");
+        bytes[8..].split_at(len)
+    }
+
+    // magic, save version, game version
+    let rest = &bytes[9..];
+    let (header1, rest) = section(rest);
+    let (header2, rest) = section(rest);
+    assert_eq!(rest[0], 0, "save has a preview");
+    let (bricks, rest) = section(&rest[1..]);
+    let (components, rest) = section(rest);
+    assert!(rest.is_empty());
+
+    vec![header1, header2, bricks, components]
+}
+
+/// The `BCD_Interact` component, with its one boolean property, used by no bricks yet.
+fn interact_component() -> Component {
+    let mut properties = BTreeMap::new();
+    properties.insert("bPlayInteractSound".into(), "Boolean".into());
+    Component {
+        version: 1,
+        brick_indices: vec![],
+        properties,
+    }
+}
+
+/// A save with one brick, which has the `BCD_Interact` component.
+fn one_brick_save() -> SaveData {
+    let mut save = SaveData::default();
+    save.components
+        .insert("BCD_Interact".into(), interact_component());
+    let mut brick = Brick::default();
+    brick.set_component_property("BCD_Interact", "bPlayInteractSound", true);
+    save.bricks.push(brick);
+    save.reconcile_components();
+    save
+}
+
+#[test]
+fn one_brick_one_component() {
+    let save = one_brick_save();
+
+    let mut bytes = vec![];
+    SaveWriter::uncompressed(&mut bytes, save).write().unwrap();
+
+    #[rustfmt::skip]
+    let expected: &[u8] = &[
+        // component count: i32
+        0x01, 0x00, 0x00, 0x00,
+        // component name: "BCD_Interact"
+        0x0d, 0x00, 0x00, 0x00,
+        b'B', b'C', b'D', b'_', b'I', b'n', b't', b'e', b'r', b'a', b'c', b't', 0x00,
+        // component bit data length: i32
+        0x34, 0x00, 0x00, 0x00,
+        // version: i32
+        0x01, 0x00, 0x00, 0x00,
+        // brick index count: i32
+        0x01, 0x00, 0x00, 0x00,
+        // brick index 0 as a single bit (max(1, 2) = 2 values), after which
+        // everything is shifted left by one bit:
+        // property count: i32 = 1
+        0x02, 0x00, 0x00, 0x00,
+        // property name: "bPlayInteractSound" (length 19)
+        0x26, 0x00, 0x00, 0x00,
+        0xc4, 0xa0, 0xd8, 0xc2, 0xf2, 0x92, 0xdc, 0xe8, 0xca, 0xe4, 0xc2, 0xc6, 0xe8, 0xa6,
+        0xde, 0xea, 0xdc, 0xc8, 0x00,
+        // property type: "Boolean" (length 8)
+        0x10, 0x00, 0x00, 0x00,
+        0x84, 0xde, 0xde, 0xd8, 0xca, 0xc2, 0xdc, 0x00,
+        // value: i32 = 1, followed by the padding bits of the final byte
+        0x02, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(sections(&bytes)[3], expected);
+
+    let save = SaveReader::new(Cursor::new(bytes))
+        .unwrap()
+        .read_all()
+        .unwrap();
+    assert_eq!(save.bricks.len(), 1);
+    assert_eq!(save.components["BCD_Interact"].brick_indices, vec![0]);
+    assert_eq!(
+        save.bricks[0].components["BCD_Interact"]["bPlayInteractSound"],
+        UnrealValue::Boolean(true)
+    );
+}
+
+#[test]
+fn embedded_in_cursor() {
+    const BEFORE: &[u8] = b"BEFORE";
+    const AFTER: &[u8] = b"AFTER";
+
+    let mut save = SaveData::default();
+    save.header1.host = Some(save.header1.author.clone());
+    save.preview = Preview::PNG(vec![1, 2, 3]);
+    save.components
+        .insert("BCD_Interact".into(), interact_component());
+    for i in 0..64 {
+        let mut brick = Brick {
+            position: (i * 10, 0, 6),
+            ..Default::default()
+        };
+        if i % 2 == 0 {
+            brick.set_component_property("BCD_Interact", "bPlayInteractSound", true);
+        }
+        save.bricks.push(brick);
+    }
+    save.header1.brick_count = save.bricks.len() as u32;
+    save.reconcile_components();
+
+    for &compressed in &[false, true] {
+        // write the save between two sentinels, starting at a nonzero offset
+        let mut cursor = Cursor::new(vec![]);
+        cursor.write_all(BEFORE).unwrap();
+        if compressed {
+            SaveWriter::new(&mut cursor, &save).write().unwrap();
+        } else {
+            SaveWriter::uncompressed(&mut cursor, &save)
+                .write()
+                .unwrap();
+        }
+        let end = cursor.position();
+        cursor.write_all(AFTER).unwrap();
+
+        // reading stops exactly at the end of the components section
+        cursor.set_position(BEFORE.len() as u64);
+        let read = SaveReader::new(&mut cursor).unwrap().read_all().unwrap();
+        assert_eq!(read, save);
+        assert_eq!(cursor.position(), end);
+
+        let mut rest = vec![];
+        cursor.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, AFTER);
+    }
+}
+
+#[test]
+fn bricks_from_iterator() {
+    let mut save = SaveData::default();
+    save.components
+        .insert("BCD_Interact".into(), interact_component());
+    for i in 0..100 {
+        let mut brick = Brick {
+            position: (i * 10, 0, 6),
+            ..Default::default()
+        };
+        if i % 3 == 0 {
+            brick.set_component_property("BCD_Interact", "bPlayInteractSound", true);
+        }
+        save.bricks.push(brick);
+    }
+    save.reconcile_components();
+
+    let mut expected = vec![];
+    SaveWriter::new(&mut expected, &save).write().unwrap();
+
+    // the same bricks, generated as they are written
+    let bricks = std::mem::take(&mut save.bricks);
+    let mut bytes = vec![];
+    SaveWriter::new(&mut bytes, &save)
+        .write_bricks_from(bricks.len(), bricks.iter().cloned())
+        .unwrap();
+    assert_eq!(bytes, expected);
+
+    let result = SaveWriter::new(vec![], &save).write_bricks_from(50, bricks.iter());
+    assert!(matches!(
+        result,
+        Err(WriteError::BrickCountMismatch {
+            expected: 50,
+            found: 100
+        })
+    ));
+    let result = SaveWriter::new(vec![], &save).write_bricks_from(101, bricks.iter());
+    assert!(matches!(
+        result,
+        Err(WriteError::BrickCountMismatch {
+            expected: 101,
+            found: 100
+        })
+    ));
+}
+
+#[test]
+fn deterministic_output() {
+    let names = [
+        "BCD_Interact",
+        "BCD_PointLight",
+        "BCD_AudioEmitter",
+        "BCD_ItemSpawn",
+    ];
+
+    let mut save = SaveData::default();
+    for (c, name) in names.iter().enumerate() {
+        let mut properties = BTreeMap::new();
+        for p in 0..6 {
+            properties.insert(format!("Property{}", p), "Byte".to_string());
+        }
+        save.components.insert(
+            name.to_string(),
+            Component {
+                version: c as i32,
+                brick_indices: vec![],
+                properties,
+            },
+        );
+    }
+    for i in 0..32u8 {
+        let mut brick = Brick {
+            position: (i as i32 * 10, 0, 6),
+            ..Default::default()
+        };
+        for (c, name) in names.iter().enumerate() {
+            if (i as usize + c) % 3 != 1 {
+                for p in 0..6 {
+                    brick.set_component_property(
+                        name,
+                        &format!("Property{}", p),
+                        UnrealValue::Byte(i + p),
+                    );
+                }
+            }
+        }
+        save.bricks.push(brick);
+    }
+    save.reconcile_components();
+
+    let write = |save: &SaveData| {
+        let mut bytes = vec![];
+        SaveWriter::new(&mut bytes, save).write().unwrap();
+        bytes
+    };
+    let bytes = write(&save);
+    assert_eq!(write(&save), bytes);
+
+    // the same save, with its maps rebuilt so they iterate in a different order
+    let mut reordered = save.clone();
+    reordered.components = save.components.clone().into_iter().collect();
+    for brick in reordered.bricks.iter_mut() {
+        brick.components = brick.components.clone().into_iter().collect();
+    }
+    assert_eq!(write(&reordered), bytes);
+}
+
+#[test]
+fn content_hash() {
+    let mut save = SaveData::default();
+    for i in 0..16 {
+        save.bricks.push(Brick {
+            position: (i * 10, 0, 6),
+            ..Default::default()
+        });
+    }
+    let hash = save.content_hash().unwrap();
+
+    // the first header and table contents don't matter, and reading back gives the same bricks
+    let mut other = save.clone();
+    other.header1.description = "Something else".into();
+    other.header2.materials[0] = "BMC_Glow".into();
+    assert_eq!(other.content_hash().unwrap(), hash);
+    let read = SaveData::from_bytes(&save.to_bytes().unwrap()).unwrap();
+    assert_eq!(read.content_hash().unwrap(), hash);
+
+    // but table lengths set the width of the bricks' indices
+    let mut longer = save.clone();
+    for i in 0..64 {
+        longer.header2.materials.push(format!("BMC_Modded{}", i));
+    }
+    assert_ne!(longer.content_hash().unwrap(), hash);
+
+    other.bricks[3].position.2 += 1;
+    assert_ne!(other.content_hash().unwrap(), hash);
+}
+
+#[test]
+fn retain_bricks() {
+    let banned = User {
+        name: "Banned".into(),
+        id: Uuid::from_u128(1),
+    };
+    let other = User {
+        name: "Other".into(),
+        id: Uuid::from_u128(2),
+    };
+
+    let mut save = SaveData::default();
+    save.header2.brick_owners = vec![banned.into(), other.into()];
+    save.header2.brick_assets = vec!["PB_DefaultBrick".into(), "PB_DefaultTile".into()];
+    save.header2.colors = vec![
+        Color::from_rgba(255, 0, 0, 255),
+        Color::from_rgba(0, 0, 255, 255),
+    ];
+    for i in 0..10u32 {
+        let mut brick = Brick {
+            position: (i as i32 * 10, 0, 6),
+            owner_index: i % 2 + 1,
+            asset_name_index: i % 2,
+            color: BrickColor::Index(i % 2),
+            ..Default::default()
+        };
+        brick.set_component_property("BCD_Interact", "bPlayInteractSound", i % 3 == 0);
+        save.bricks.push(brick);
+    }
+    save.components
+        .insert("BCD_Interact".into(), interact_component());
+    save.reconcile_components();
+
+    // remove everything the first owner built
+    save.retain_bricks(|brick| brick.owner_index != 1);
+    assert_eq!(save.bricks.len(), 5);
+    assert_eq!(save.header1.brick_count, 5);
+    assert_eq!(
+        save.components["BCD_Interact"].brick_indices,
+        vec![0, 1, 2, 3, 4]
+    );
+    assert_eq!(save.header2.brick_owners[0].bricks, 0);
+    assert_eq!(save.header2.brick_owners[1].bricks, 5);
+    save.validate().unwrap();
+
+    // the first asset and color are now unused
+    assert_eq!(save.compact_tables(), 2);
+    assert_eq!(
+        save.header2.brick_assets,
+        vec!["PB_DefaultTile".to_string()]
+    );
+    assert_eq!(save.header2.colors, vec![Color::from_rgba(0, 0, 255, 255)]);
+    assert!(save
+        .bricks
+        .iter()
+        .all(|brick| brick.asset_name_index == 0 && brick.color == BrickColor::Index(0)));
+    save.validate().unwrap();
+
+    let read = SaveData::from_bytes(&save.to_bytes().unwrap()).unwrap();
+    assert_eq!(read.bricks, save.bricks);
+}
+
+#[test]
+fn empty_save() {
+    let mut save = SaveData::default();
+    save.header2.colors.clear();
+    save.header2.materials.clear();
+    save.header2.brick_assets.clear();
+
+    // declared, but used by no brick
+    save.components
+        .insert("BCD_Interact".into(), interact_component());
+
+    let bytes = save.to_bytes().unwrap();
+
+    // unused components aren't written
+    let read = SaveData::from_bytes(&bytes).unwrap();
+    assert!(read.bricks.is_empty());
+    assert!(read.components.is_empty());
+    assert_eq!(read.header1.brick_count, 0);
+    assert!(read.header2.colors.is_empty());
+
+    let mut reader = SaveReader::new(Cursor::new(&bytes)).unwrap();
+    let header1 = reader.read_header1().unwrap();
+    let header2 = reader.read_header2().unwrap();
+    reader.read_preview().unwrap();
+    let partial = reader.read_bricks_limited(&header1, &header2, 10).unwrap();
+    assert!(partial.bricks.is_empty());
+    assert!(!partial.is_truncated());
+}
+
+#[test]
+fn progress() {
+    let mut save = SaveData::default();
+    save.components
+        .insert("BCD_Interact".into(), interact_component());
+    for i in 0..10_000 {
+        let mut brick = Brick {
+            position: (i, 0, 6),
+            ..Default::default()
+        };
+        brick.set_component_property("BCD_Interact", "bPlayInteractSound", true);
+        save.bricks.push(brick);
+    }
+    save.reconcile_components();
+
+    let mut expected = vec![];
+    SaveWriter::new(&mut expected, &save).write().unwrap();
+
+    // progress doesn't change the output, and the callback can borrow from the caller
+    let mut stages = vec![];
+    let mut bytes = vec![];
+    SaveWriter::new(&mut bytes, &save)
+        .on_progress(|stage, done, total| stages.push((stage, done, total)))
+        .write()
+        .unwrap();
+    assert_eq!(bytes, expected);
+
+    assert_eq!(
+        stages,
+        [
+            (WriteStage::Header1, 1, 1),
+            (WriteStage::Header2, 1, 1),
+            (WriteStage::Preview, 1, 1),
+            (WriteStage::Bricks, 4096, 10_000),
+            (WriteStage::Bricks, 8192, 10_000),
+            (WriteStage::Bricks, 10_000, 10_000),
+            (WriteStage::Components, 1, 1),
+        ]
+    );
+}
+
+#[test]
+fn append() {
+    fn save(property: &str, value: UnrealValue) -> SaveData {
+        let mut save = SaveData::default();
+        save.header2.colors = vec![Color::from_rgba(255, 0, 0, 255)];
+
+        let mut properties = BTreeMap::new();
+        properties.insert(property.into(), value.type_name().into());
+        save.components.insert(
+            "BCD_Interact".into(),
+            Component {
+                version: 1,
+                brick_indices: vec![0],
+                properties,
+            },
+        );
+
+        let mut brick = Brick::default();
+        let mut props = HashMap::new();
+        props.insert(property.into(), value);
+        brick.components.insert("BCD_Interact".into(), props);
+        save.bricks.push(brick);
+        save.header1.brick_count = 1;
+        save
+    }
+
+    let mut a = save("bPlayInteractSound", UnrealValue::Boolean(true));
+    let mut b = save("Message", UnrealValue::String("hi".into()));
+    b.header2.brick_assets.push("B_Unused".into());
+    b.header2.colors = vec![
+        Color::from_rgba(0, 0, 255, 255),
+        Color::from_rgba(255, 0, 0, 255),
+    ];
+    b.bricks[0].color = BrickColor::Index(1);
+    let assets = a.header2.brick_assets.clone();
+    a.append(b);
+
+    // only table entries the appended bricks use are added
+    assert_eq!(a.header2.brick_assets, assets);
+    assert_eq!(a.header2.colors.len(), 1);
+    assert_eq!(a.bricks[1].color, BrickColor::Index(0));
+
+    let mut bytes = vec![];
+    SaveWriter::new(&mut bytes, a).write().unwrap();
+    let read = SaveReader::new(&bytes[..]).unwrap().read_all().unwrap();
+
+    assert_eq!(read.bricks.len(), 2);
+    assert_eq!(read.components["BCD_Interact"].brick_indices, [0, 1]);
+    let interact = |brick: usize, property: &str| {
+        read.bricks[brick].components["BCD_Interact"][property].clone()
+    };
+    assert_eq!(
+        interact(0, "bPlayInteractSound"),
+        UnrealValue::Boolean(true)
+    );
+    assert_eq!(interact(0, "Message"), UnrealValue::String(String::new()));
+    assert_eq!(
+        interact(1, "bPlayInteractSound"),
+        UnrealValue::Boolean(false)
+    );
+    assert_eq!(interact(1, "Message"), UnrealValue::String("hi".into()));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_writer() {
+    use brickadia::write::AsyncSaveWriter;
+
+    let mut save = one_brick_save();
+    save.header1.host = Some(save.header1.author.clone());
+    save.header1.brick_count = 1;
+    let mut expected = vec![];
+    SaveWriter::new(&mut expected, &save).write().unwrap();
+
+    let mut stages = vec![];
+    let mut bytes = vec![];
+    AsyncSaveWriter::new(&mut bytes, &save)
+        .on_progress(|stage, _, _| stages.push(stage))
+        .write()
+        .await
+        .unwrap();
+    assert_eq!(bytes, expected);
+    assert_eq!(SaveData::from_bytes(&bytes).unwrap(), save);
+    assert_eq!(
+        stages,
+        [
+            WriteStage::Header1,
+            WriteStage::Header2,
+            WriteStage::Preview,
+            WriteStage::Bricks,
+            WriteStage::Components,
+        ]
+    );
+}
+
+#[test]
+fn save_to_path_atomic() {
+    let dir = std::env::temp_dir().join(format!("brickadia-atomic-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("test.brs");
+    let tmp = dir.join("test.brs.tmp");
+
+    let save = one_brick_save();
+    save.save_to_path_atomic(&path).unwrap();
+    let read = SaveData::from_bytes(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(read.bricks, save.bricks);
+    assert!(!tmp.exists());
+
+    // a save that fails to write leaves the existing file alone
+    let mut broken = save.clone();
+    broken.bricks[0].components.clear();
+    broken.bricks[0].set_component_property("BCD_Interact", "Other", true);
+    assert!(matches!(
+        broken.save_to_path_atomic(&path),
+        Err(WriteError::MissingProperty { .. })
+    ));
+    let read = SaveData::from_bytes(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(read.bricks, save.bricks);
+    assert!(!tmp.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn estimate_size() {
+    let mut save = one_brick_save();
+    save.preview = Preview::PNG(vec![1, 2, 3, 4]);
+    for i in 1..100 {
+        save.bricks.push(Brick {
+            position: (i * 10, 0, 6),
+            ..Default::default()
+        });
+    }
+
+    let estimate = save.estimate_size().unwrap();
+    let sections = SaveWriter::new(vec![], &save).write_sections().unwrap();
+    assert_eq!(estimate.header1, sections.header1.len());
+    assert_eq!(estimate.header2, sections.header2.len());
+    assert_eq!(estimate.bricks, sections.bricks.len());
+    assert_eq!(estimate.components, sections.components.len());
+
+    // the uncompressed file is exactly the estimate, and compressing only makes it smaller
+    let mut bytes = vec![];
+    SaveWriter::uncompressed(&mut bytes, &save).write().unwrap();
+    assert_eq!(bytes.len(), estimate.total());
+    let compressed = save.to_bytes().unwrap();
+    assert!(compressed.len() <= estimate.total());
+
+    // it fails where writing would
+    save.bricks[0].components.clear();
+    save.bricks[0].set_component_property("BCD_Interact", "Other", true);
+    assert!(matches!(
+        save.estimate_size(),
+        Err(WriteError::MissingProperty { .. })
+    ));
+}
+
+#[test]
+fn compression_disabled() {
+    let mut save = one_brick_save();
+    for i in 1..200 {
+        save.bricks.push(Brick {
+            position: (i * 10, 0, 6),
+            ..Default::default()
+        });
+    }
+
+    let mut expected = vec![];
+    SaveWriter::uncompressed(&mut expected, &save)
+        .write()
+        .unwrap();
+
+    // every section is stored uncompressed, as with `uncompressed`
+    let mut bytes = vec![];
+    let mut writer = SaveWriter::new(&mut bytes, &save);
+    writer.set_compression_disabled(true);
+    writer.write().unwrap();
+    assert_eq!(sections(&bytes).len(), 4);
+    assert_eq!(bytes, expected);
+
+    // and back on again
+    let mut bytes = vec![];
+    let mut writer = SaveWriter::uncompressed(&mut bytes, &save);
+    writer.set_compression_disabled(false);
+    writer.write().unwrap();
+    assert_eq!(bytes, save.to_bytes().unwrap());
+    assert!(bytes.len() < expected.len());
+}
+
+#[test]
+fn owner_brick_count_too_large() {
+    let mut save = one_brick_save();
+    save.header2.brick_owners.push(BrickOwner {
+        name: "Owner".into(),
+        id: Uuid::from_u128(1),
+        bricks: u32::MAX,
+    });
+    assert!(matches!(
+        save.to_bytes(),
+        Err(WriteError::TooLarge {
+            section: "brick owner bricks",
+            len,
+        }) if len == u32::MAX as usize
+    ));
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "zip")]
+
+use std::{env, fs, io::Write, path::PathBuf, process};
+
+use brickadia::{
+    read::{list_saves, ReadError, SaveReader},
+    save::{Brick, SaveData},
+};
+use zip::{write::FileOptions, ZipWriter};
+
+/// Write a zip archive of `entries` to a new file in the temporary directory.
+fn archive(name: &str, entries: &[(&str, &[u8])]) -> PathBuf {
+    let path = env::temp_dir().join(format!("brickadia-{}-{}.zip", name, process::id()));
+    let mut zip = ZipWriter::new(fs::File::create(&path).unwrap());
+    for (name, bytes) in entries {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(bytes).unwrap();
+    }
+    zip.finish().unwrap();
+    path
+}
+
+#[test]
+fn read_from_zip() {
+    let mut save = SaveData::default();
+    save.bricks.push(Brick {
+        position: (1, 2, 3),
+        ..Default::default()
+    });
+    let bytes = save.to_bytes().unwrap();
+
+    let path = archive(
+        "pack",
+        &[
+            ("readme.txt", b"not a save"),
+            ("maps/b.brs", &bytes),
+            ("maps/A.BRS", &bytes),
+        ],
+    );
+
+    assert_eq!(list_saves(&path).unwrap(), ["maps/A.BRS", "maps/b.brs"]);
+
+    let read = SaveReader::from_zip(&path, "maps/b.brs")
+        .unwrap()
+        .read_all()
+        .unwrap();
+    assert_eq!(read.bricks, save.bricks);
+
+    assert!(matches!(
+        SaveReader::from_zip(&path, "maps/missing.brs"),
+        Err(ReadError::ZipError(_))
+    ));
+    assert!(matches!(
+        SaveReader::from_zip(&path, "readme.txt"),
+        Err(ReadError::BadMagic)
+    ));
+
+    fs::remove_file(path).unwrap();
+}